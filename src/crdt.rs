@@ -0,0 +1,28 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+pub mod g_counter;
+pub mod g_set;
+pub mod lww_map;
+pub mod lww_register;
+pub mod mv_register;
+pub mod or_set;
+pub mod pn_counter;
+pub mod replicator;
+pub mod two_p_set;
+
+/// Conflict-free replicated data type.
+/// Replicas can be updated independently and converge once every replica has merged the
+/// updates of all other replicas (directly or through other replicas), regardless of the order
+/// or how often the states are merged.
+pub trait Crdt: Clone + Default + PartialEq + Serialize + DeserializeOwned {
+    type Value;
+
+    /// Merges the other state into this one (commutative, associative and idempotent).
+    /// Returns true if this state changed.
+    fn merge(&mut self, other: &Self) -> bool;
+    /// Returns the smallest state that, merged into known, results in the same state as
+    /// merging this state into known.
+    /// Returns the default (empty) state if known already contains this state.
+    fn delta_since(&self, known: &Self) -> Self;
+    fn value(&self) -> Self::Value;
+}
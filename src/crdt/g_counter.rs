@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{crdt::Crdt, types::node_id::NodeId};
+
+/// Grow-only counter where every Node only increments its own count.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GCounter {
+    pub counts: BTreeMap<NodeId, u64>,
+}
+
+impl GCounter {
+    pub fn increment(&mut self, node_id: NodeId, amount: u64) {
+        *self.counts.entry(node_id).or_default() += amount;
+    }
+}
+
+impl Crdt for GCounter {
+    type Value = u64;
+
+    /// Keeps the highest count of every Node.
+    fn merge(&mut self, other: &Self) -> bool {
+        let mut changed = false;
+        for (node_id, count) in other.counts.iter() {
+            let own = self.counts.entry(*node_id).or_default();
+            if *own < *count {
+                *own = *count;
+                changed = true;
+            }
+        }
+        changed
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            counts: self
+                .counts
+                .iter()
+                .filter(|(node_id, count)| known.counts.get(node_id).is_none_or(|k| k < count))
+                .map(|(node_id, count)| (*node_id, *count))
+                .collect(),
+        }
+    }
+    fn value(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
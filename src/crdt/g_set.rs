@@ -0,0 +1,49 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::crdt::Crdt;
+
+/// Grow-only set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GSet<T: Ord> {
+    pub elements: BTreeSet<T>,
+}
+
+impl<T: Ord> Default for GSet<T> {
+    fn default() -> Self {
+        Self {
+            elements: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Ord> GSet<T> {
+    /// Returns true if the element is new.
+    pub fn add(&mut self, element: T) -> bool {
+        self.elements.insert(element)
+    }
+    pub fn contains(&self, element: &T) -> bool {
+        self.elements.contains(element)
+    }
+}
+
+impl<T> Crdt for GSet<T>
+where
+    T: Ord + Clone + Serialize + DeserializeOwned,
+{
+    type Value = BTreeSet<T>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let len = self.elements.len();
+        self.elements.extend(other.elements.iter().cloned());
+        self.elements.len() != len
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            elements: self.elements.difference(&known.elements).cloned().collect(),
+        }
+    }
+    fn value(&self) -> BTreeSet<T> {
+        self.elements.clone()
+    }
+}
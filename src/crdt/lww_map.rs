@@ -0,0 +1,79 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    crdt::{lww_register::LwwRegister, Crdt},
+    types::node_id::NodeId,
+};
+
+/// Map where every key is a LwwRegister, removed keys are kept as cleared registers.
+/// NOTE: The keys have to be serializable as JSON object keys (strings or integers).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LwwMap<K: Ord, V> {
+    pub entries: BTreeMap<K, LwwRegister<V>>,
+}
+
+impl<K: Ord, V> Default for LwwMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord, V> LwwMap<K, V> {
+    pub fn insert(&mut self, key: K, value: V, timestamp: u64, node_id: NodeId) {
+        self.entries
+            .entry(key)
+            .or_default()
+            .set(value, timestamp, node_id);
+    }
+    pub fn remove(&mut self, key: K, timestamp: u64, node_id: NodeId) {
+        self.entries
+            .entry(key)
+            .or_default()
+            .write(None, timestamp, node_id);
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).and_then(|r| r.value.as_ref())
+    }
+}
+
+impl<K, V> Crdt for LwwMap<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    type Value = BTreeMap<K, V>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let mut changed = false;
+        for (key, register) in other.entries.iter() {
+            changed |= self.entries.entry(key.clone()).or_default().merge(register);
+        }
+        changed
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        let empty = LwwRegister::default();
+        Self {
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, register)| {
+                    (
+                        key,
+                        register.delta_since(known.entries.get(key).unwrap_or(&empty)),
+                    )
+                })
+                .filter(|(_, delta)| *delta != empty)
+                .map(|(key, delta)| (key.clone(), delta))
+                .collect(),
+        }
+    }
+    fn value(&self) -> BTreeMap<K, V> {
+        self.entries
+            .iter()
+            .filter_map(|(key, register)| Some((key.clone(), register.value.clone()?)))
+            .collect()
+    }
+}
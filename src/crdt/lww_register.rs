@@ -0,0 +1,62 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{crdt::Crdt, types::node_id::NodeId};
+
+/// Last-writer-wins register: the write with the highest (timestamp, Node Id) wins.
+/// A value of None means the register has not been written or has been cleared.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LwwRegister<T> {
+    pub value: Option<T>,
+    pub timestamp: u64,
+    pub node_id: Option<NodeId>,
+}
+
+impl<T> Default for LwwRegister<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            timestamp: 0,
+            node_id: None,
+        }
+    }
+}
+
+impl<T> LwwRegister<T> {
+    pub fn set(&mut self, value: T, timestamp: u64, node_id: NodeId) {
+        self.write(Some(value), timestamp, node_id);
+    }
+    /// NOTE: The timestamp is increased if it is not newer than the current one, so a Node
+    /// always overwrites the writes it has already seen.
+    pub fn write(&mut self, value: Option<T>, timestamp: u64, node_id: NodeId) {
+        self.value = value;
+        self.timestamp = timestamp.max(self.timestamp + 1);
+        self.node_id = Some(node_id);
+    }
+    fn is_newer_than(&self, other: &Self) -> bool {
+        (self.timestamp, self.node_id) > (other.timestamp, other.node_id)
+    }
+}
+
+impl<T> Crdt for LwwRegister<T>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    type Value = Option<T>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        if other.is_newer_than(self) {
+            *self = other.clone();
+            return true;
+        }
+        false
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        match self.is_newer_than(known) {
+            true => self.clone(),
+            false => Self::default(),
+        }
+    }
+    fn value(&self) -> Option<T> {
+        self.value.clone()
+    }
+}
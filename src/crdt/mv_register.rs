@@ -0,0 +1,76 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{crdt::Crdt, types::node_id::NodeId};
+
+pub type VersionVector = BTreeMap<NodeId, u64>;
+
+/// Multi-value register: keeps all concurrent writes (writes whose version vectors do not
+/// dominate each other) until a write that has observed all of them overwrites them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MvRegister<T: Ord> {
+    pub entries: BTreeSet<(T, VersionVector)>,
+}
+
+impl<T: Ord> Default for MvRegister<T> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> MvRegister<T> {
+    /// Overwrites all values this Node has observed.
+    pub fn set(&mut self, node_id: NodeId, value: T) {
+        let mut version = VersionVector::new();
+        for (_, v) in self.entries.iter() {
+            for (node, counter) in v.iter() {
+                let own = version.entry(*node).or_default();
+                *own = (*own).max(*counter);
+            }
+        }
+        *version.entry(node_id).or_default() += 1;
+
+        self.entries = BTreeSet::from([(value, version)]);
+    }
+}
+
+/// Returns true if v1 has observed everything v2 has observed and more.
+fn dominates(v1: &VersionVector, v2: &VersionVector) -> bool {
+    v1 != v2
+        && v2
+            .iter()
+            .all(|(node, counter)| v1.get(node).is_some_and(|c| c >= counter))
+}
+
+impl<T> Crdt for MvRegister<T>
+where
+    T: Ord + Clone + Serialize + DeserializeOwned,
+{
+    type Value = Vec<T>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let entries: BTreeSet<(T, VersionVector)> =
+            self.entries.union(&other.entries).cloned().collect();
+        let entries: BTreeSet<(T, VersionVector)> = entries
+            .iter()
+            .filter(|(_, v1)| !entries.iter().any(|(_, v2)| dominates(v2, v1)))
+            .cloned()
+            .collect();
+
+        let changed = entries != self.entries;
+        self.entries = entries;
+        changed
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            entries: self.entries.difference(&known.entries).cloned().collect(),
+        }
+    }
+    /// Returns all concurrently written values.
+    fn value(&self) -> Vec<T> {
+        let values: BTreeSet<&T> = self.entries.iter().map(|(value, _)| value).collect();
+        values.into_iter().cloned().collect()
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::{crdt::Crdt, types::node_id::NodeId};
+
+/// Unique tag of an add operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Dot {
+    pub node_id: NodeId,
+    pub counter: u64,
+}
+
+/// Observed-remove set: removing an element only removes the adds that have been observed, so
+/// concurrent adds win and removed elements can be added again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrSet<T: Ord> {
+    /// Elements with the Dots of the adds that have not been removed.
+    pub adds: BTreeSet<(T, Dot)>,
+    /// Dots of the removed adds (tombstones).
+    pub removed: BTreeSet<Dot>,
+}
+
+impl<T: Ord> Default for OrSet<T> {
+    fn default() -> Self {
+        Self {
+            adds: BTreeSet::new(),
+            removed: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> OrSet<T> {
+    pub fn add(&mut self, node_id: NodeId, element: T) {
+        let counter = self
+            .adds
+            .iter()
+            .map(|(_, dot)| dot)
+            .chain(self.removed.iter())
+            .filter(|dot| dot.node_id == node_id)
+            .map(|dot| dot.counter)
+            .max()
+            .unwrap_or(0);
+        self.adds.insert((
+            element,
+            Dot {
+                node_id,
+                counter: counter + 1,
+            },
+        ));
+    }
+    /// Removes all observed adds of the element.
+    /// Returns false if the element is not in the set.
+    pub fn remove(&mut self, element: &T) -> bool {
+        let removed: Vec<(T, Dot)> = self
+            .adds
+            .iter()
+            .filter(|(e, _)| e == element)
+            .cloned()
+            .collect();
+        for add in removed.iter() {
+            self.adds.remove(add);
+            self.removed.insert(add.1);
+        }
+        !removed.is_empty()
+    }
+    pub fn contains(&self, element: &T) -> bool {
+        self.adds.iter().any(|(e, _)| e == element)
+    }
+}
+
+impl<T> Crdt for OrSet<T>
+where
+    T: Ord + Clone + Serialize + DeserializeOwned,
+{
+    type Value = BTreeSet<T>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let len = (self.adds.len(), self.removed.len());
+        self.removed.extend(other.removed.iter().cloned());
+        self.adds.extend(other.adds.iter().cloned());
+        let removed = &self.removed;
+        self.adds.retain(|(_, dot)| !removed.contains(dot));
+        (self.adds.len(), self.removed.len()) != len
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            adds: self.adds.difference(&known.adds).cloned().collect(),
+            removed: self.removed.difference(&known.removed).cloned().collect(),
+        }
+    }
+    fn value(&self) -> BTreeSet<T> {
+        self.adds.iter().map(|(e, _)| e.clone()).collect()
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crdt::{g_counter::GCounter, Crdt},
+    types::node_id::NodeId,
+};
+
+/// Counter that can be incremented and decremented, made of one GCounter for the increments
+/// and one for the decrements.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PnCounter {
+    pub increments: GCounter,
+    pub decrements: GCounter,
+}
+
+impl PnCounter {
+    pub fn add(&mut self, node_id: NodeId, delta: i64) {
+        match delta >= 0 {
+            true => self.increments.increment(node_id, delta.unsigned_abs()),
+            false => self.decrements.increment(node_id, delta.unsigned_abs()),
+        }
+    }
+}
+
+impl Crdt for PnCounter {
+    type Value = i64;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let increments = self.increments.merge(&other.increments);
+        let decrements = self.decrements.merge(&other.decrements);
+        increments || decrements
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            increments: self.increments.delta_since(&known.increments),
+            decrements: self.decrements.delta_since(&known.decrements),
+        }
+    }
+    fn value(&self) -> i64 {
+        self.increments.value() as i64 - self.decrements.value() as i64
+    }
+}
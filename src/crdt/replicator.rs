@@ -0,0 +1,155 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    crdt::Crdt,
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_id::NodeId, node_info::NodeInfo, packet::Packet,
+        payload::Payload,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplicationMode {
+    /// Send the whole state to every neighbour every "interval".
+    State,
+    /// Send every neighbour only the part of the state it is not known to have every
+    /// "interval". The state a neighbour is known to have grows with every state received from
+    /// and acknowledged by it.
+    Delta,
+}
+
+/// Replicates a Crdt to the neighbours in the Topology.
+/// Multiple Crdts can be replicated by the same Node, their states are told apart by "name".
+/// Requires the PacketHandler to have a tick interval.
+pub struct CrdtReplicator<T: Crdt> {
+    pub name: String,
+    pub crdt: T,
+    pub mode: ReplicationMode,
+    pub interval: Duration,
+    /// When the state has last been sent to the neighbours.
+    pub last_gossip: Instant,
+    /// The state each neighbour is known to have (Delta mode).
+    pub known_by: HashMap<NodeId, T>,
+}
+
+impl<T: Crdt> CrdtReplicator<T> {
+    pub fn new(name: &str, mode: ReplicationMode, interval: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            crdt: T::default(),
+            mode,
+            interval,
+            last_gossip: Instant::now(),
+            known_by: HashMap::new(),
+        }
+    }
+    fn add_known_by(&mut self, node_id: &NodeId, state: &T) {
+        self.known_by.entry(*node_id).or_default().merge(state);
+    }
+    /// Parses the state if it belongs to this Crdt.
+    fn parse_state(&self, name: &str, state: &serde_json::Value) -> Option<T> {
+        match name == self.name {
+            true => serde_json::from_value(state.clone()).ok(),
+            false => None,
+        }
+    }
+    fn to_payload(&self, state: &T) -> serde_json::Value {
+        serde_json::to_value(state).unwrap_or_default()
+    }
+}
+
+impl<T: Crdt> MessageHandler for CrdtReplicator<T> {
+    fn handle_message(
+        &mut self,
+        packet: &Packet,
+        _state: &NodeInfo,
+    ) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::CrdtState { name, state },
+                        ..
+                    },
+                ..
+            } => {
+                let Some(state) = self.parse_state(name, state) else {
+                    return Collection::None;
+                };
+                self.crdt.merge(&state);
+                // NOTE: The received state is known to the source Node, so it is acknowledged
+                // to let the source Node know that it is known to this Node as well.
+                match self.mode {
+                    ReplicationMode::State => Collection::None,
+                    ReplicationMode::Delta => {
+                        self.add_known_by(src, &state);
+                        Collection::One(MessageResponse::NoAck {
+                            src: Option::None,
+                            dest: *src,
+                            in_reply_to: Option::None,
+                            payload: Payload::CrdtStateOk {
+                                name: name.clone(),
+                                state: self.to_payload(&state),
+                            },
+                        })
+                    }
+                }
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::CrdtStateOk { name, state },
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(state) = self.parse_state(name, state) {
+                    self.add_known_by(src, &state);
+                }
+                Collection::None
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        if self.last_gossip.elapsed() < self.interval {
+            return Collection::None;
+        }
+        self.last_gossip = Instant::now();
+
+        // NOTE: The states are sent without Ack, because the next gossip contains the same
+        // (or a newer) state anyway.
+        let empty = T::default();
+        state
+            .get_neighbours()
+            .into_iter()
+            .filter_map(|n| {
+                let state = match self.mode {
+                    ReplicationMode::State => self.crdt.clone(),
+                    ReplicationMode::Delta => self
+                        .crdt
+                        .delta_since(self.known_by.get(n).unwrap_or(&empty)),
+                };
+                match state == empty {
+                    true => None,
+                    false => Some(MessageResponse::NoAck {
+                        src: Option::None,
+                        dest: *n,
+                        in_reply_to: Option::None,
+                        payload: Payload::CrdtState {
+                            name: self.name.clone(),
+                            state: self.to_payload(&state),
+                        },
+                    }),
+                }
+            })
+            .collect::<Vec<MessageResponse>>()
+            .into()
+    }
+}
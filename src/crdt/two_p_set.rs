@@ -0,0 +1,68 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::crdt::{g_set::GSet, Crdt};
+
+/// Set made of a GSet for the added and a GSet for the removed elements.
+/// Removed elements can not be added again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TwoPSet<T: Ord> {
+    pub added: GSet<T>,
+    pub removed: GSet<T>,
+}
+
+impl<T: Ord> Default for TwoPSet<T> {
+    fn default() -> Self {
+        Self {
+            added: GSet::default(),
+            removed: GSet::default(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> TwoPSet<T> {
+    /// Returns false if the element has already been removed.
+    pub fn add(&mut self, element: T) -> bool {
+        if self.removed.contains(&element) {
+            return false;
+        }
+        self.added.add(element);
+        true
+    }
+    /// Returns false if the element is not in the set.
+    pub fn remove(&mut self, element: T) -> bool {
+        if !self.contains(&element) {
+            return false;
+        }
+        self.removed.add(element)
+    }
+    pub fn contains(&self, element: &T) -> bool {
+        self.added.contains(element) && !self.removed.contains(element)
+    }
+}
+
+impl<T> Crdt for TwoPSet<T>
+where
+    T: Ord + Clone + Serialize + DeserializeOwned,
+{
+    type Value = BTreeSet<T>;
+
+    fn merge(&mut self, other: &Self) -> bool {
+        let added = self.added.merge(&other.added);
+        let removed = self.removed.merge(&other.removed);
+        added || removed
+    }
+    fn delta_since(&self, known: &Self) -> Self {
+        Self {
+            added: self.added.delta_since(&known.added),
+            removed: self.removed.delta_since(&known.removed),
+        }
+    }
+    fn value(&self) -> BTreeSet<T> {
+        self.added
+            .elements
+            .difference(&self.removed.elements)
+            .cloned()
+            .collect()
+    }
+}
@@ -1,3 +1,9 @@
 pub mod broadcast_handler;
+pub mod datomic_handler;
 pub mod echo_handler;
+pub mod g_set_handler;
 pub mod generate_handler;
+pub mod lin_kv_handler;
+pub mod pn_counter_handler;
+pub mod sharded_kv_handler;
+pub mod txn_list_append_handler;
@@ -1,10 +1,159 @@
+use rand::seq::SliceRandom;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
 use crate::types::{
-    collection::Collection, message::Message, message_handler::MessageHandler,
-    message_response::MessageResponse, node_info::NodeInfo, packet::Packet, payload::Payload,
+    collection::Collection,
+    message::Message,
+    message_handler::MessageHandler,
+    message_response::MessageResponse,
+    message_store::MessageStore,
+    node_id::NodeId,
+    node_info::NodeInfo,
+    packet::Packet,
+    payload::{Payload, ReadValue},
 };
 
+pub enum BroadcastMode {
+    /// Forward every Broadcast once along the Broadcast Topology.
+    Forward,
+    /// Forward every Broadcast once and additionally exchange the messages the other Node is
+    /// missing with "fanout" random neighbours every "interval" (anti-entropy), so Broadcasts
+    /// that got lost (e.g. during a network partition) are eventually received by every Node.
+    /// Requires the PacketHandler to have a tick interval.
+    Gossip { interval: Duration, fanout: usize },
+    /// Do not forward Broadcasts immediately, instead send every neighbour all messages it is
+    /// not known to have as one batch every "interval". A message is known to a neighbour once
+    /// it has been acknowledged or received from it.
+    /// Requires the PacketHandler to have a tick interval.
+    Delta { interval: Duration },
+}
+
+/// What this Node and a neighbour have received of each other's messages (Gossip and Delta
+/// mode).
+/// NOTE: The MessageStore keeps the insertion order, so a Node acknowledges the messages of
+/// the other Node by the number of its messages it has received.
+#[derive(Debug, Default)]
+pub struct PeerSync {
+    /// The neighbour has the first "acked" messages of this Node.
+    pub acked: usize,
+    /// This Node has the first "received" messages of the neighbour.
+    pub received: usize,
+    /// Messages received from the neighbour, they are never sent back to it.
+    pub known: HashSet<usize>,
+}
+
 pub struct BroadcastHandler {
-    pub messages: Vec<usize>,
+    pub messages: MessageStore,
+    pub mode: BroadcastMode,
+    /// When the messages have last been synced with the neighbours (Gossip and Delta mode).
+    pub last_sync: Instant,
+    /// What each neighbour has received (Gossip and Delta mode).
+    pub peers: HashMap<NodeId, PeerSync>,
+}
+
+impl BroadcastHandler {
+    pub fn new(mode: BroadcastMode) -> Self {
+        Self {
+            messages: MessageStore::new(),
+            mode,
+            last_sync: Instant::now(),
+            peers: HashMap::new(),
+        }
+    }
+    fn sync_due(&mut self, interval: Duration) -> bool {
+        if self.last_sync.elapsed() < interval {
+            return false;
+        }
+        self.last_sync = Instant::now();
+        true
+    }
+    /// NOTE: The Gossip is sent even if the neighbour is not missing anything, so it sends
+    /// back the messages this Node is missing.
+    fn gossip(&mut self, state: &NodeInfo, fanout: usize) -> Collection<MessageResponse> {
+        state
+            .get_neighbours()
+            .choose_multiple(&mut rand::thread_rng(), fanout)
+            .map(|n| {
+                let (messages, up_to, received) = self.missing_by(n);
+                MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: **n,
+                    in_reply_to: Option::None,
+                    payload: Payload::Gossip {
+                        messages,
+                        up_to,
+                        received,
+                    },
+                }
+            })
+            .collect::<Vec<MessageResponse>>()
+            .into()
+    }
+    /// Returns the messages the neighbour is not known to have, the number of messages they
+    /// cover and the number of messages received from the neighbour.
+    fn missing_by(&mut self, node_id: &NodeId) -> (Vec<usize>, usize, usize) {
+        let peer = self.peers.entry(*node_id).or_default();
+        // NOTE: Messages received from the neighbour do not have to be acknowledged by it, so
+        // they are not scanned again.
+        peer.acked += self
+            .messages
+            .iter()
+            .skip(peer.acked)
+            .take_while(|m| peer.known.contains(m))
+            .count();
+        let messages = self
+            .messages
+            .iter()
+            .skip(peer.acked)
+            .filter(|m| !peer.known.contains(m))
+            .cloned()
+            .collect();
+        (messages, self.messages.len(), peer.received)
+    }
+    /// Stores the messages the neighbour sent and what it has received.
+    fn receive_from(
+        &mut self,
+        node_id: &NodeId,
+        messages: &[usize],
+        up_to: usize,
+        received: usize,
+    ) {
+        self.messages.extend(messages);
+        let peer = self.peers.entry(*node_id).or_default();
+        peer.known.extend(messages.iter().cloned());
+        peer.received = peer.received.max(up_to);
+        peer.acked = peer.acked.max(received);
+    }
+    /// NOTE: Neighbours that have acknowledged all messages are skipped without scanning the
+    /// messages.
+    fn send_deltas(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        let len = self.messages.len();
+        state
+            .get_neighbours()
+            .into_iter()
+            .filter(|n| self.peers.get(*n).is_none_or(|p| p.acked < len))
+            .cloned()
+            .collect::<Vec<NodeId>>()
+            .into_iter()
+            .filter_map(|n| match self.missing_by(&n) {
+                (messages, _, _) if messages.is_empty() => None,
+                (messages, up_to, received) => Some(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: n,
+                    in_reply_to: Option::None,
+                    payload: Payload::BroadcastDelta {
+                        messages,
+                        up_to,
+                        received,
+                    },
+                }),
+            })
+            .collect::<Vec<MessageResponse>>()
+            .into()
+    }
 }
 
 impl MessageHandler for BroadcastHandler {
@@ -20,15 +169,29 @@ impl MessageHandler for BroadcastHandler {
                     },
                 ..
             } => {
-                self.messages.push(*message);
+                let is_new = self.messages.insert(*message);
+                if let BroadcastMode::Delta { .. } = self.mode {
+                    // NOTE: The message is sent to the neighbours with the next delta.
+                    return match state.conn_info.contains_key(src) {
+                        true => Collection::None,
+                        false => Collection::One(MessageResponse::NoAck {
+                            src: Option::None,
+                            dest: *src,
+                            in_reply_to: *msg_id,
+                            payload: Payload::BroadcastOk,
+                        }),
+                    };
+                }
                 match state.broadcast_topology.get(src) {
+                    // NOTE: Already known messages have already been forwarded.
+                    Some(_) if !is_new => Collection::None,
                     // Node is an internal Server Node
                     Some(nodes) => Collection::Multiple(
                         nodes
                             .iter()
                             .map(|n| MessageResponse::NoAck {
-                                src: Option::Some(src.clone()),
-                                dest: n.clone(),
+                                src: Option::Some(*src),
+                                dest: *n,
                                 in_reply_to: Option::None,
                                 payload: Payload::Broadcast { message: *message },
                             })
@@ -36,19 +199,22 @@ impl MessageHandler for BroadcastHandler {
                     ),
                     // Node is an external Client Node
                     None => {
-                        let nodes = &state.broadcast_topology[&state.node_id];
+                        let nodes = match is_new {
+                            true => &state.broadcast_topology[&state.node_id][..],
+                            false => &[],
+                        };
                         let mut responses = Vec::with_capacity(nodes.len() + 1);
 
                         responses.push(MessageResponse::NoAck {
                             src: Option::None,
-                            dest: src.clone(),
+                            dest: *src,
                             in_reply_to: *msg_id,
                             payload: Payload::BroadcastOk,
                         });
 
                         responses.extend(nodes.iter().map(|n| MessageResponse::NoAck {
-                            src: Option::Some(state.node_id.clone()),
-                            dest: n.clone(),
+                            src: Option::Some(state.node_id),
+                            dest: *n,
                             in_reply_to: Option::None,
                             payload: Payload::Broadcast { message: *message },
                         }));
@@ -62,19 +228,114 @@ impl MessageHandler for BroadcastHandler {
                 body:
                     Message {
                         msg_id,
-                        payload: Payload::Read,
+                        payload: Payload::Read { .. },
                         ..
                     },
                 ..
             } => Collection::One(MessageResponse::NoAck {
                 src: Option::None,
-                dest: src.clone(),
+                dest: *src,
                 in_reply_to: *msg_id,
-                payload: Payload::ReadOk {
-                    messages: self.messages.clone(),
-                },
+                payload: Payload::ReadOk(ReadValue::Messages {
+                    messages: self.messages.snapshot(),
+                }),
             }),
+            // NOTE: The GossipOk is always sent, because it acknowledges the Gossip.
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::Gossip {
+                                messages,
+                                up_to,
+                                received,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                self.receive_from(src, messages, *up_to, *received);
+                let (messages, up_to, received) = self.missing_by(src);
+                Collection::One(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: Option::None,
+                    payload: Payload::GossipOk {
+                        messages,
+                        up_to,
+                        received,
+                    },
+                })
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::GossipOk {
+                                messages,
+                                up_to,
+                                received,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                self.receive_from(src, messages, *up_to, *received);
+                Collection::None
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::BroadcastDelta {
+                                messages,
+                                up_to,
+                                received,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                self.receive_from(src, messages, *up_to, *received);
+                Collection::One(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: Option::None,
+                    payload: Payload::BroadcastDeltaOk {
+                        received: self.peers[src].received,
+                    },
+                })
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::BroadcastDeltaOk { received },
+                        ..
+                    },
+                ..
+            } => {
+                let peer = self.peers.entry(*src).or_default();
+                peer.acked = peer.acked.max(*received);
+                Collection::None
+            }
             _ => Collection::None,
         }
     }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        match self.mode {
+            BroadcastMode::Forward => Collection::None,
+            BroadcastMode::Gossip { interval, fanout } => match self.sync_due(interval) {
+                true => self.gossip(state, fanout),
+                false => Collection::None,
+            },
+            BroadcastMode::Delta { interval } => match self.sync_due(interval) {
+                true => self.send_deltas(state),
+                false => Collection::None,
+            },
+        }
+    }
 }
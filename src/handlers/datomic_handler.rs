@@ -0,0 +1,317 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use crate::{
+    services::kv::{KvClient, KvReply},
+    txn::MicroOp,
+    types::{
+        collection::Collection,
+        id_strategy::{IdGenerator, IdStrategy},
+        message::Message,
+        message_handler::MessageHandler,
+        message_response::MessageResponse,
+        node_id::NodeId,
+        node_info::NodeInfo,
+        packet::Packet,
+        payload::{Payload, ReadValue},
+    },
+};
+
+/// Key of the root pointer in "lin-kv".
+const ROOT: &str = "root";
+
+/// Transaction that is being executed by the DatomicHandler.
+pub struct DatomicTxn {
+    pub client: NodeId,
+    pub msg_id: Option<usize>,
+    pub txn: Vec<MicroOp>,
+    /// The id of the map thunk once the root has been read (Some(None) if there is no root).
+    pub root: Option<Option<serde_json::Value>>,
+    /// The new root and the completed micro-operations once the new thunks have been written.
+    pub commit: Option<(serde_json::Value, Vec<MicroOp>)>,
+    /// The completed micro-operations of the last attempt whose "cas" failed.
+    /// NOTE: A "cas" that is sent again after a timeout fails if the first one has been applied
+    /// and other transactions might have advanced the root since, so the attempt has succeeded
+    /// if the lists of the current root contain its appended elements (the elements appended
+    /// to a key are unique in "txn-list-append").
+    pub failed: Option<Vec<MicroOp>>,
+    /// Number of requests to the services that have not been answered.
+    pub outstanding: usize,
+}
+
+/// Handles the "txn-list-append" workload like the transactor of Maelstrom's Datomic tutorial.
+/// The database is an immutable tree of thunks in "lww-kv": a map thunk with the id of the list
+/// thunk of every key. A transaction reads the id of the map thunk from the root in "lin-kv",
+/// reads the thunks it needs, writes new thunks for the lists it appends to and a new map thunk
+/// and then advances the root with a "cas". If the root has changed in the meantime, the
+/// transaction is executed again from the new root.
+/// Thunks never change, so they are cached once they have been read or written.
+/// Requires the PacketHandler to have a tick interval.
+pub struct DatomicHandler {
+    pub lin_kv: KvClient<u64>,
+    pub lww_kv: KvClient<u64>,
+    /// Generates the ids of the thunks.
+    pub ids: IdGenerator,
+    /// The cached thunks by the JSON of their id.
+    pub thunks: HashMap<String, serde_json::Value>,
+    pub txns: HashMap<u64, DatomicTxn>,
+    pub next_txn: u64,
+}
+
+impl DatomicHandler {
+    /// Requests to the services are sent again if they have not been answered within the
+    /// timeout.
+    pub fn new(strategy: IdStrategy, timeout: Duration) -> Self {
+        Self {
+            lin_kv: KvClient::new("lin-kv", timeout),
+            lww_kv: KvClient::new("lww-kv", timeout),
+            ids: IdGenerator::new(strategy),
+            thunks: HashMap::new(),
+            txns: HashMap::new(),
+            next_txn: 0,
+        }
+    }
+    fn thunk(&self, id: &serde_json::Value) -> Option<&serde_json::Value> {
+        self.thunks.get(&id.to_string())
+    }
+    fn list(&self, id: Option<&serde_json::Value>) -> Vec<i64> {
+        id.and_then(|id| self.thunk(id))
+            .and_then(|list| serde_json::from_value(list.clone()).ok())
+            .unwrap_or_default()
+    }
+    /// Sends the next requests of the transaction once all of its requests have been answered.
+    fn advance(&mut self, number: u64, state: &NodeInfo) -> Vec<MessageResponse> {
+        let Some(txn) = self.txns.get(&number) else {
+            return Vec::with_capacity(0);
+        };
+        if txn.outstanding > 0 {
+            return Vec::with_capacity(0);
+        }
+        let root = match &txn.root {
+            Some(root) => root.clone(),
+            None => return self.request(number, |s| s.lin_kv.read(ROOT.into(), number)),
+        };
+        let map: BTreeMap<i64, serde_json::Value> = match &root {
+            Some(id) => match self.thunk(id) {
+                Some(map) => serde_json::from_value(map.clone()).unwrap_or_default(),
+                None => return self.request(number, |s| s.lww_kv.read(id.clone(), number)),
+            },
+            None => BTreeMap::new(),
+        };
+
+        let mut missing: Vec<serde_json::Value> = Vec::new();
+        for op in txn.txn.iter() {
+            let (MicroOp::Read { key, .. } | MicroOp::Append { key, .. }) = op;
+            match map.get(key) {
+                Some(id) if self.thunk(id).is_none() && !missing.contains(id) => {
+                    missing.push(id.clone())
+                }
+                _ => {}
+            }
+        }
+        if !missing.is_empty() {
+            return missing
+                .into_iter()
+                .flat_map(|id| self.request(number, |s| s.lww_kv.read(id, number)))
+                .collect();
+        }
+
+        if let Some((new_root, _)) = &txn.commit {
+            let (from, to) = (root.clone().unwrap_or_default(), new_root.clone());
+            let create = root.is_none();
+            return self.request(number, |s| {
+                s.lin_kv.cas(ROOT.into(), from, to, create, number)
+            });
+        }
+        self.execute(number, map, state)
+    }
+    /// Executes the micro-operations on the thunks and writes the new thunks.
+    fn execute(
+        &mut self,
+        number: u64,
+        mut map: BTreeMap<i64, serde_json::Value>,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        // NOTE: See DatomicTxn::failed.
+        let failed = self.txns.get_mut(&number).and_then(|t| t.failed.take());
+        if let Some(result) = failed.filter(|result| self.applied(result, &map)) {
+            return self.reply(number, result);
+        }
+        let Some(txn) = self.txns.get(&number) else {
+            return Vec::with_capacity(0);
+        };
+        let mut lists: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+        let result: Vec<MicroOp> = txn
+            .txn
+            .iter()
+            .map(|op| match op {
+                MicroOp::Read { key, .. } => MicroOp::Read {
+                    key: *key,
+                    value: Some(
+                        lists
+                            .get(key)
+                            .cloned()
+                            .unwrap_or_else(|| self.list(map.get(key))),
+                    ),
+                },
+                MicroOp::Append { key, element } => {
+                    lists
+                        .entry(*key)
+                        .or_insert_with(|| self.list(map.get(key)))
+                        .push(*element);
+                    op.clone()
+                }
+            })
+            .collect();
+        // NOTE: Read-only transactions are linearized when the root is read.
+        if lists.is_empty() {
+            return self.reply(number, result);
+        }
+
+        let mut thunks = Vec::new();
+        for (key, list) in lists {
+            let id = self.next_id(state);
+            map.insert(key, id.clone());
+            thunks.push((id, serde_json::json!(list)));
+        }
+        let root = self.next_id(state);
+        thunks.push((root.clone(), serde_json::json!(map)));
+        if let Some(txn) = self.txns.get_mut(&number) {
+            txn.commit = Some((root, result));
+        }
+        thunks
+            .into_iter()
+            .flat_map(|(id, thunk)| {
+                self.thunks.insert(id.to_string(), thunk.clone());
+                self.request(number, |s| s.lww_kv.write(id, thunk, number))
+            })
+            .collect()
+    }
+    /// Whether the lists of the map thunk contain all elements appended by the micro-operations.
+    fn applied(&self, result: &[MicroOp], map: &BTreeMap<i64, serde_json::Value>) -> bool {
+        result.iter().all(|op| match op {
+            MicroOp::Append { key, element } => self.list(map.get(key)).contains(element),
+            MicroOp::Read { .. } => true,
+        })
+    }
+    fn next_id(&mut self, state: &NodeInfo) -> serde_json::Value {
+        let id = self.ids.next_id(state.node_number, state.node_ids.len());
+        serde_json::to_value(id).unwrap_or_default()
+    }
+    /// Sends a request for the transaction.
+    fn request(
+        &mut self,
+        number: u64,
+        send: impl FnOnce(&mut Self) -> MessageResponse,
+    ) -> Vec<MessageResponse> {
+        if let Some(txn) = self.txns.get_mut(&number) {
+            txn.outstanding += 1;
+        }
+        vec![send(self)]
+    }
+    fn reply(&mut self, number: u64, result: Vec<MicroOp>) -> Vec<MessageResponse> {
+        match self.txns.remove(&number) {
+            Some(txn) => vec![MessageResponse::NoAck {
+                src: Option::None,
+                dest: txn.client,
+                in_reply_to: txn.msg_id,
+                payload: Payload::TxnOk { txn: result },
+            }],
+            None => Vec::with_capacity(0),
+        }
+    }
+    fn handle_root_reply(&mut self, reply: KvReply<u64>, state: &NodeInfo) -> Vec<MessageResponse> {
+        let number = reply.waiter;
+        let Some(txn) = self.txns.get_mut(&number) else {
+            return Vec::with_capacity(0);
+        };
+        txn.outstanding -= 1;
+        match (reply.request, reply.reply) {
+            (Payload::Read { .. }, Payload::ReadOk(ReadValue::Value { value })) => {
+                txn.root = Some(Some(value))
+            }
+            (Payload::Read { .. }, Payload::Error { code: 20, .. }) => txn.root = Some(None),
+            (Payload::Cas { .. }, Payload::CasOk) => {
+                let result = txn.commit.take().map(|(_, r)| r).unwrap_or_default();
+                return self.reply(number, result);
+            }
+            // NOTE: The root has changed, so the transaction is executed again.
+            (Payload::Cas { .. }, _) => {
+                txn.root = None;
+                txn.failed = txn.commit.take().map(|(_, result)| result);
+            }
+            (request, _) => {
+                return self.request(number, |s| s.lin_kv.send(request, number));
+            }
+        }
+        self.advance(number, state)
+    }
+    fn handle_thunk_reply(
+        &mut self,
+        reply: KvReply<u64>,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        let number = reply.waiter;
+        let Some(txn) = self.txns.get_mut(&number) else {
+            return Vec::with_capacity(0);
+        };
+        txn.outstanding -= 1;
+        match (reply.request, reply.reply) {
+            (Payload::Read { key: Some(id) }, Payload::ReadOk(ReadValue::Value { value })) => {
+                self.thunks.insert(id.to_string(), value);
+            }
+            (Payload::Write { .. }, Payload::WriteOk) => {}
+            // NOTE: Thunks that have just been written might not be visible yet.
+            (request, _) => {
+                return self.request(number, |s| s.lww_kv.send(request, number));
+            }
+        }
+        self.advance(number, state)
+    }
+}
+
+impl MessageHandler for DatomicHandler {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        if let Some(reply) = self.lin_kv.handle_reply(packet) {
+            return self.handle_root_reply(reply, state).into();
+        }
+        if let Some(reply) = self.lww_kv.handle_reply(packet) {
+            return self.handle_thunk_reply(reply, state).into();
+        }
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::Txn { txn },
+                        ..
+                    },
+                ..
+            } => {
+                self.next_txn += 1;
+                self.txns.insert(
+                    self.next_txn,
+                    DatomicTxn {
+                        client: *src,
+                        msg_id: *msg_id,
+                        txn: txn.clone(),
+                        root: None,
+                        commit: None,
+                        failed: None,
+                        outstanding: 0,
+                    },
+                );
+                self.advance(self.next_txn, state).into()
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+        let mut responses = self.lin_kv.handle_tick();
+        responses.extend(self.lww_kv.handle_tick());
+        responses.into()
+    }
+}
@@ -24,7 +24,7 @@ impl MessageHandler for EchoHandler {
         {
             Collection::One(MessageResponse::NoAck {
                 src: Option::None,
-                dest: src.clone(),
+                dest: *src,
                 in_reply_to: *msg_id,
                 payload: Payload::EchoOk { echo: echo.clone() },
             })
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use crate::{
+    crdt::{
+        g_set::GSet,
+        replicator::{CrdtReplicator, ReplicationMode},
+        Crdt,
+    },
+    types::{
+        collection::Collection,
+        message::Message,
+        message_handler::MessageHandler,
+        message_response::MessageResponse,
+        node_id::NodeId,
+        node_info::NodeInfo,
+        packet::Packet,
+        payload::{AddValue, Payload, ReadValue},
+    },
+};
+
+pub enum GSetMode {
+    /// Send every newly added element to all neighbours with Ack, so every element is
+    /// eventually received by every Node as long as the Topology stays connected.
+    /// NOTE: Lost elements are only sent again if the PacketHandler has a tick interval and a
+    /// resend timeout (see DeliveryConfig), otherwise use the State mode.
+    Operation,
+    /// Merge the set with the neighbours every "interval" (see CrdtReplicator).
+    /// Requires the PacketHandler to have a tick interval.
+    State {
+        replication: ReplicationMode,
+        interval: Duration,
+    },
+}
+
+/// Handles the "g-set" workload.
+pub struct GSetHandler {
+    pub set: CrdtReplicator<GSet<i64>>,
+    pub mode: GSetMode,
+}
+
+impl GSetHandler {
+    pub fn new(mode: GSetMode) -> Self {
+        // NOTE: The replicator is only ticked in State mode, but still merges received states.
+        let set = match mode {
+            GSetMode::Operation => {
+                CrdtReplicator::new("g-set", ReplicationMode::State, Duration::MAX)
+            }
+            GSetMode::State {
+                replication,
+                interval,
+            } => CrdtReplicator::new("g-set", replication, interval),
+        };
+        Self { set, mode }
+    }
+    /// Adds the element and sends it to the neighbours (except the source Node) if it is new
+    /// and the Operation mode is used.
+    fn add(&mut self, element: i64, src: &NodeId, state: &NodeInfo) -> Vec<MessageResponse> {
+        if !self.set.crdt.add(element) || !matches!(self.mode, GSetMode::Operation) {
+            return Vec::with_capacity(0);
+        }
+        state
+            .get_neighbours()
+            .into_iter()
+            .filter(|n| *n != src)
+            .map(|n| MessageResponse::Ack {
+                src: Option::None,
+                dest: *n,
+                in_reply_to: Option::None,
+                payload: Payload::GSetAdd { element },
+            })
+            .collect()
+    }
+}
+
+impl MessageHandler for GSetHandler {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::Add(AddValue::Element { element }),
+                        ..
+                    },
+                ..
+            } => {
+                let mut responses = self.add(*element, src, state);
+                responses.push(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: *msg_id,
+                    payload: Payload::AddOk,
+                });
+                responses.into()
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::GSetAdd { element },
+                        ..
+                    },
+                ..
+            } => self.add(*element, src, state).into(),
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::Read { .. },
+                        ..
+                    },
+                ..
+            } => Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: *msg_id,
+                payload: Payload::ReadOk(ReadValue::Elements {
+                    value: self.set.crdt.value().into_iter().collect(),
+                }),
+            }),
+            _ => self.set.handle_message(packet, state),
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        match self.mode {
+            GSetMode::Operation => Collection::None,
+            GSetMode::State { .. } => self.set.handle_tick(state),
+        }
+    }
+}
@@ -1,10 +1,24 @@
 use crate::types::{
-    collection::Collection, message::Message, message_handler::MessageHandler,
-    message_response::MessageResponse, node_info::NodeInfo, packet::Packet, payload::Payload,
+    collection::Collection,
+    id_strategy::{IdGenerator, IdStrategy},
+    message::Message,
+    message_handler::MessageHandler,
+    message_response::MessageResponse,
+    node_info::NodeInfo,
+    packet::Packet,
+    payload::Payload,
 };
 
 pub struct GenerateHandler {
-    pub counter: usize,
+    pub generator: IdGenerator,
+}
+
+impl GenerateHandler {
+    pub fn new(strategy: IdStrategy) -> Self {
+        Self {
+            generator: IdGenerator::new(strategy),
+        }
+    }
 }
 
 impl MessageHandler for GenerateHandler {
@@ -24,13 +38,14 @@ impl MessageHandler for GenerateHandler {
             ..
         } = packet
         {
-            self.counter += 1;
             Collection::One(MessageResponse::NoAck {
                 src: Option::None,
-                dest: src.clone(),
+                dest: *src,
                 in_reply_to: *msg_id,
                 payload: Payload::GenerateOk {
-                    id: self.counter * _state.node_ids.len() + _state.node_number,
+                    id: self
+                        .generator
+                        .next_id(_state.node_number, _state.node_ids.len()),
                 },
             })
         } else {
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    paxos::PaxosHandler,
+    raft::RaftHandler,
+    replication::StateMachine,
+    types::payload::{Payload, ReadValue},
+};
+
+/// Handles the "lin-kv" workload.
+pub type LinKvHandler = RaftHandler<KvStore>;
+/// Handles the "lin-kv" workload using Multi-Paxos instead of Raft.
+pub type PaxosLinKvHandler = PaxosHandler<KvStore>;
+
+/// Key-value store with the "read", "write" and "cas" operations of the Maelstrom KV
+/// services.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KvStore {
+    /// The values indexed by the JSON of their key.
+    pub store: HashMap<String, serde_json::Value>,
+}
+
+impl KvStore {
+    fn key_does_not_exist(key: &serde_json::Value) -> Payload {
+        Payload::Error {
+            code: 20,
+            text: format!("Key {} does not exist", key),
+        }
+    }
+}
+
+impl StateMachine for KvStore {
+    fn is_request(&self, payload: &Payload) -> bool {
+        matches!(
+            payload,
+            Payload::Read { key: Some(_) } | Payload::Write { .. } | Payload::Cas { .. }
+        )
+    }
+    fn apply(&mut self, request: &Payload) -> Payload {
+        match request {
+            Payload::Read { key: Some(key) } => match self.store.get(&key.to_string()) {
+                Some(value) => Payload::ReadOk(ReadValue::Value {
+                    value: value.clone(),
+                }),
+                None => Self::key_does_not_exist(key),
+            },
+            Payload::Write { key, value } => {
+                self.store.insert(key.to_string(), value.clone());
+                Payload::WriteOk
+            }
+            Payload::Cas {
+                key,
+                from,
+                to,
+                create_if_not_exists,
+            } => match self.store.get(&key.to_string()) {
+                Some(value) if value == from => {
+                    self.store.insert(key.to_string(), to.clone());
+                    Payload::CasOk
+                }
+                Some(value) => Payload::Error {
+                    code: 22,
+                    text: format!("Expected {}, but had {}", from, value),
+                },
+                None if *create_if_not_exists => {
+                    self.store.insert(key.to_string(), to.clone());
+                    Payload::CasOk
+                }
+                None => Self::key_does_not_exist(key),
+            },
+            _ => Payload::Error {
+                code: 10,
+                text: "Operation is not supported".to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use crate::{
+    crdt::{
+        pn_counter::PnCounter,
+        replicator::{CrdtReplicator, ReplicationMode},
+        Crdt,
+    },
+    types::{
+        collection::Collection,
+        message::Message,
+        message_handler::MessageHandler,
+        message_response::MessageResponse,
+        node_info::NodeInfo,
+        packet::Packet,
+        payload::{AddValue, Payload, ReadValue},
+    },
+};
+
+/// Handles the "pn-counter" workload.
+/// Every Node counts the additions it received from Clients in its own entry of the counter
+/// and sends the neighbours the counts they are missing every "interval", so the counters
+/// converge once all Nodes can reach each other again.
+/// Requires the PacketHandler to have a tick interval.
+pub struct PnCounterHandler {
+    pub counter: CrdtReplicator<PnCounter>,
+}
+
+impl PnCounterHandler {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            counter: CrdtReplicator::new("pn-counter", ReplicationMode::Delta, interval),
+        }
+    }
+}
+
+impl MessageHandler for PnCounterHandler {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::Add(AddValue::Delta { delta }),
+                        ..
+                    },
+                ..
+            } => {
+                self.counter.crdt.add(state.node_id, *delta);
+                Collection::One(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: *msg_id,
+                    payload: Payload::AddOk,
+                })
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::Read { .. },
+                        ..
+                    },
+                ..
+            } => Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: *msg_id,
+                payload: Payload::ReadOk(ReadValue::Counter {
+                    value: self.counter.crdt.value(),
+                }),
+            }),
+            _ => self.counter.handle_message(packet, state),
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        self.counter.handle_tick(state)
+    }
+}
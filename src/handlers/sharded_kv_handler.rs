@@ -0,0 +1,218 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    handlers::lin_kv_handler::KvStore,
+    replication::StateMachine,
+    sharding::{HashRing, Sharded, ShardedHandler},
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_id::NodeId, node_info::NodeInfo, packet::Packet,
+        payload::Payload,
+    },
+};
+
+/// Handles the "lin-kv" workload by partitioning the keys over the Server Nodes.
+pub type ShardedKvHandler = ShardedHandler<KvHandler>;
+
+/// Key-value store with a single copy of every key.
+/// Linearizable as long as every key is only handled by its owner (see ShardedKvHandler), but
+/// the keys of a Node are lost if it crashes.
+/// After a rebalance, every member of the old ring sends a handoff (possibly empty) to every
+/// other member of the new ring. The keys stay in the store until their handoff has been
+/// acknowledged and the handoff is sent again every handoff timeout until then.
+/// Requests for keys whose previous owner's handoff has not arrived yet are deferred until it
+/// arrives.
+/// NOTE: Every Node has to apply the same membership changes in the same order, so their
+/// epochs match. Requests for the keys of a crashed previous owner are deferred forever.
+pub struct KvHandler {
+    pub store: KvStore,
+    /// Number of rebalances, handoffs of other epochs are ignored.
+    pub epoch: u64,
+    /// The ring before the last rebalance, to find the previous owner of a key.
+    pub previous: HashRing,
+    /// Nodes whose handoff has not arrived yet.
+    pub incoming: BTreeSet<NodeId>,
+    /// Requests for keys that are still being handed in.
+    pub deferred: Vec<Packet>,
+    /// Handoffs that have not been acknowledged by their new owner and when they have been
+    /// sent last.
+    pub outgoing: HashMap<NodeId, (Vec<(serde_json::Value, serde_json::Value)>, Instant)>,
+    pub handoff_timeout: Duration,
+}
+
+impl KvHandler {
+    pub fn new(handoff_timeout: Duration) -> Self {
+        Self {
+            store: KvStore::default(),
+            epoch: 0,
+            previous: HashRing::default(),
+            incoming: BTreeSet::new(),
+            deferred: Vec::new(),
+            outgoing: HashMap::new(),
+            handoff_timeout,
+        }
+    }
+    fn handoff(
+        epoch: u64,
+        owner: NodeId,
+        entries: &[(serde_json::Value, serde_json::Value)],
+    ) -> MessageResponse {
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest: owner,
+            in_reply_to: Option::None,
+            payload: Payload::ShardHandoff {
+                epoch,
+                entries: entries.to_vec(),
+            },
+        }
+    }
+    /// Returns true if the previous owner of the key has not handed it over yet.
+    fn is_incoming(&self, payload: &Payload) -> bool {
+        self.shard_key(payload)
+            .and_then(|key| self.previous.owner(&key))
+            .is_some_and(|owner| self.incoming.contains(&owner))
+    }
+    /// Handles the deferred requests whose keys have been handed in.
+    fn handle_deferred(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        let deferred = std::mem::take(&mut self.deferred);
+        deferred
+            .into_iter()
+            .fold(Collection::None, |responses, packet| {
+                responses + self.handle_message(&packet, state)
+            })
+    }
+}
+
+impl MessageHandler for KvHandler {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::ShardHandoff { epoch, entries },
+                        ..
+                    },
+                ..
+            } if *epoch == self.epoch => {
+                // NOTE: Retried handoffs are only acknowledged, because the keys might have
+                // been written since the first one arrived.
+                if self.incoming.remove(src) {
+                    for (key, value) in entries {
+                        self.store.store.insert(key.to_string(), value.clone());
+                    }
+                }
+                Collection::One(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: Option::None,
+                    payload: Payload::ShardHandoffOk { epoch: *epoch },
+                }) + self.handle_deferred(state)
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::ShardHandoffOk { epoch },
+                        ..
+                    },
+                ..
+            } if *epoch == self.epoch => {
+                if let Some((entries, _)) = self.outgoing.remove(src) {
+                    for (key, _) in entries {
+                        self.store.store.remove(&key.to_string());
+                    }
+                }
+                Collection::None
+            }
+            Packet {
+                body: Message { payload, .. },
+                ..
+            } if self.store.is_request(payload) && self.is_incoming(payload) => {
+                self.deferred.push(packet.clone());
+                Collection::None
+            }
+            Packet {
+                src,
+                body: Message {
+                    msg_id, payload, ..
+                },
+                ..
+            } if self.store.is_request(payload) => Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: *msg_id,
+                payload: self.store.apply(payload),
+            }),
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+        let mut responses = Vec::new();
+        for (owner, (entries, sent)) in self.outgoing.iter_mut() {
+            if sent.elapsed() >= self.handoff_timeout {
+                *sent = Instant::now();
+                responses.push(Self::handoff(self.epoch, *owner, entries));
+            }
+        }
+        responses.into()
+    }
+}
+
+impl Sharded for KvHandler {
+    fn shard_key(&self, payload: &Payload) -> Option<serde_json::Value> {
+        match payload {
+            Payload::Read { key: Some(key) }
+            | Payload::Write { key, .. }
+            | Payload::Cas { key, .. } => Some(key.clone()),
+            _ => None,
+        }
+    }
+    /// Hands the keys owned by other Nodes over to their owners and waits for the handoffs of
+    /// the previous owners.
+    fn rebalance(
+        &mut self,
+        old: &HashRing,
+        new: &HashRing,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        self.epoch += 1;
+        self.previous = old.clone();
+        // NOTE: There are no keys to hand over when the ring is built for the first time.
+        if old.members.is_empty() {
+            return Vec::with_capacity(0);
+        }
+        self.incoming = old
+            .members
+            .iter()
+            .filter(|m| **m != state.node_id)
+            .cloned()
+            .collect();
+
+        self.outgoing = new
+            .members
+            .iter()
+            .filter(|m| **m != state.node_id)
+            .map(|m| (*m, (Vec::new(), Instant::now())))
+            .collect();
+        if old.members.contains(&state.node_id) {
+            for (key, value) in self.store.store.iter() {
+                let key: serde_json::Value = serde_json::from_str(key).unwrap_or_default();
+                if let Some((entries, _)) = new
+                    .owner(&key)
+                    .and_then(|owner| self.outgoing.get_mut(&owner))
+                {
+                    entries.push((key, value.clone()));
+                }
+            }
+        }
+        self.outgoing
+            .iter()
+            .map(|(owner, (entries, _))| Self::handoff(self.epoch, *owner, entries))
+            .collect()
+    }
+}
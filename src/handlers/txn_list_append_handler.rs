@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    txn::{
+        hlc::{HybridClock, Timestamp},
+        mvcc::{Commit, MvccStore, Transaction},
+        MicroOp,
+    },
+    types::{
+        collection::Collection, message_handler::MessageHandler, message_response::MessageResponse,
+        node_id::NodeId, node_info::NodeInfo, packet::Packet, payload::Payload,
+    },
+};
+
+/// Transaction that waits for its certification or for its commit to be installed.
+pub struct CertifyingTxn {
+    pub client: NodeId,
+    pub msg_id: Option<usize>,
+    pub txn: Vec<MicroOp>,
+    /// The index of the commit in the log once the transaction has been certified.
+    pub index: Option<u64>,
+}
+
+/// Handles the "txn-list-append" workload with snapshot isolation.
+/// Every transaction reads from the snapshot of the commits the Node has installed and
+/// buffers its appends. The commits are validated and ordered by a single certifier (the
+/// lowest Server Node): it aborts a transaction if another transaction has committed to one
+/// of the keys it appended to after its snapshot (first committer wins), otherwise it appends
+/// the commit to its log with a timestamp of its HybridClock and sends it to the other Nodes.
+/// Every Node installs the commits in the order of the log, so a snapshot is always a prefix of
+/// the log and commits that arrive late can not change it.
+/// Nodes ask the certifier for the commits they are missing every sync interval, so lost
+/// commits are installed eventually.
+/// NOTE: The certifier is a single point of failure.
+/// Requires the PacketHandler to have a tick interval and a resend timeout (see
+/// DeliveryConfig), because certifications are sent with Ack.
+pub struct TxnListAppendHandler {
+    pub store: MvccStore,
+    pub clock: HybridClock,
+    /// The installed commits in the order of the certifier, the index of a commit is its
+    /// position + 1.
+    pub log: Vec<Commit>,
+    /// Commits that arrived before an earlier one, by their index.
+    pub buffered: BTreeMap<u64, Commit>,
+    pub pending: HashMap<u64, CertifyingTxn>,
+    pub next_number: u64,
+    pub sync_interval: Duration,
+    pub last_sync: Instant,
+}
+
+impl TxnListAppendHandler {
+    pub fn new(clock: HybridClock, sync_interval: Duration) -> Self {
+        Self {
+            store: MvccStore::default(),
+            clock,
+            log: Vec::new(),
+            buffered: BTreeMap::new(),
+            pending: HashMap::new(),
+            next_number: 0,
+            sync_interval,
+            last_sync: Instant::now(),
+        }
+    }
+    pub fn certifier(state: &NodeInfo) -> NodeId {
+        state
+            .server_nodes
+            .iter()
+            .min()
+            .cloned()
+            .unwrap_or(state.node_id)
+    }
+    /// The commit timestamp of the last installed commit.
+    fn snapshot(&self) -> Timestamp {
+        self.log.last().map(|c| c.commit_ts).unwrap_or_default()
+    }
+    fn reply(client: NodeId, msg_id: Option<usize>, payload: Payload) -> MessageResponse {
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest: client,
+            in_reply_to: msg_id,
+            payload,
+        }
+    }
+    fn conflict(client: NodeId, msg_id: Option<usize>, key: i64) -> MessageResponse {
+        Self::reply(
+            client,
+            msg_id,
+            Payload::Error {
+                code: 30,
+                text: format!("Transaction conflicts on key {}", key),
+            },
+        )
+    }
+    /// Certifies the transaction on the certifier and sends the commit to the other Nodes.
+    /// Returns the index of the commit or the conflicting key.
+    fn certify(
+        &mut self,
+        txn: &Transaction,
+        state: &NodeInfo,
+        responses: &mut Vec<MessageResponse>,
+    ) -> Result<u64, i64> {
+        let commit_ts = self.clock.now(state.node_number);
+        self.store.commit(txn, commit_ts)?;
+        let commit = Commit {
+            commit_ts,
+            appends: txn.appends.clone(),
+        };
+        self.log.push(commit.clone());
+        let index = self.log.len() as u64;
+
+        responses.extend(
+            state
+                .server_nodes
+                .iter()
+                .filter(|n| **n != state.node_id)
+                .map(|n| Self::committed(*n, index, &commit)),
+        );
+        Ok(index)
+    }
+    fn committed(dest: NodeId, index: u64, commit: &Commit) -> MessageResponse {
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest,
+            in_reply_to: Option::None,
+            payload: Payload::TxnCommitted {
+                index,
+                commit_ts: commit.commit_ts,
+                appends: commit.appends.clone(),
+            },
+        }
+    }
+    /// Installs the commit and all buffered commits that follow it.
+    fn install(&mut self, index: u64, commit: Commit) {
+        if index <= self.log.len() as u64 {
+            return;
+        }
+        self.buffered.insert(index, commit);
+        while let Some(commit) = self.buffered.remove(&(self.log.len() as u64 + 1)) {
+            self.clock.observe(commit.commit_ts);
+            self.store.install(commit.commit_ts, &commit.appends);
+            self.log.push(commit);
+        }
+        let installed = self.log.len() as u64;
+        self.buffered.retain(|index, _| *index > installed);
+    }
+    /// Replies to the transactions whose commits have been installed.
+    fn release_installed(&mut self) -> Vec<MessageResponse> {
+        let installed = self.log.len() as u64;
+        let numbers: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.index.is_some_and(|i| i <= installed))
+            .map(|(number, _)| *number)
+            .collect();
+        numbers
+            .into_iter()
+            .filter_map(|number| self.pending.remove(&number))
+            .map(|p| Self::reply(p.client, p.msg_id, Payload::TxnOk { txn: p.txn }))
+            .collect()
+    }
+    fn handle_txn(
+        &mut self,
+        client: NodeId,
+        msg_id: Option<usize>,
+        txn: &[MicroOp],
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        let mut transaction = Transaction::new(self.snapshot());
+        let txn = transaction.execute(&self.store, txn);
+        // NOTE: Read-only transactions do not need to be certified.
+        if transaction.appends.is_empty() {
+            return vec![Self::reply(client, msg_id, Payload::TxnOk { txn })];
+        }
+
+        let certifier = Self::certifier(state);
+        if certifier == state.node_id {
+            let mut responses = Vec::new();
+            let reply = match self.certify(&transaction, state, &mut responses) {
+                Ok(_) => Self::reply(client, msg_id, Payload::TxnOk { txn }),
+                Err(key) => Self::conflict(client, msg_id, key),
+            };
+            responses.push(reply);
+            return responses;
+        }
+
+        self.next_number += 1;
+        self.pending.insert(
+            self.next_number,
+            CertifyingTxn {
+                client,
+                msg_id,
+                txn,
+                index: None,
+            },
+        );
+        vec![MessageResponse::Ack {
+            src: Option::None,
+            dest: certifier,
+            in_reply_to: Option::None,
+            payload: Payload::TxnCertify {
+                number: self.next_number,
+                start_ts: transaction.start_ts,
+                appends: transaction.appends,
+            },
+        }]
+    }
+}
+
+impl MessageHandler for TxnListAppendHandler {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match &packet.body.payload {
+            Payload::Txn { txn } => self
+                .handle_txn(packet.src, packet.body.msg_id, txn, state)
+                .into(),
+            Payload::TxnCertify {
+                number,
+                start_ts,
+                appends,
+            } => {
+                let transaction = Transaction {
+                    start_ts: *start_ts,
+                    appends: appends.clone(),
+                };
+                let mut responses = Vec::new();
+                let result = self.certify(&transaction, state, &mut responses);
+                responses.push(MessageResponse::Ack {
+                    src: Option::None,
+                    dest: packet.src,
+                    in_reply_to: Option::None,
+                    payload: Payload::TxnCertified {
+                        number: *number,
+                        result,
+                    },
+                });
+                responses.into()
+            }
+            Payload::TxnCertified { number, result } => match result {
+                Ok(index) => {
+                    if let Some(pending) = self.pending.get_mut(number) {
+                        pending.index = Some(*index);
+                    }
+                    // NOTE: The reply waits until the commit has been installed, so later
+                    // transactions on this Node see it.
+                    self.release_installed().into()
+                }
+                Err(key) => match self.pending.remove(number) {
+                    Some(p) => Collection::One(Self::conflict(p.client, p.msg_id, *key)),
+                    None => Collection::None,
+                },
+            },
+            Payload::TxnCommitted {
+                index,
+                commit_ts,
+                appends,
+            } => {
+                let commit = Commit {
+                    commit_ts: *commit_ts,
+                    appends: appends.clone(),
+                };
+                self.install(*index, commit);
+                self.release_installed().into()
+            }
+            Payload::TxnSync { index } => self
+                .log
+                .iter()
+                .enumerate()
+                .skip(*index as usize)
+                .map(|(i, commit)| Self::committed(packet.src, i as u64 + 1, commit))
+                .collect::<Vec<MessageResponse>>()
+                .into(),
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        let certifier = Self::certifier(state);
+        if certifier == state.node_id || self.last_sync.elapsed() < self.sync_interval {
+            return Collection::None;
+        }
+        self.last_sync = Instant::now();
+        Collection::One(MessageResponse::NoAck {
+            src: Option::None,
+            dest: certifier,
+            in_reply_to: Option::None,
+            payload: Payload::TxnSync {
+                index: self.log.len() as u64,
+            },
+        })
+    }
+}
@@ -3,40 +3,2639 @@
 pub mod packet_handler;
 pub use packet_handler::PacketHandler;
 
+pub mod topology_analysis;
+
 pub mod types;
 pub use types::{
     collection::Collection,
+    delivery_config::DeliveryConfig,
+    id_strategy::{Clock, IdGenerator, IdStrategy, SystemClock, UniqueId},
     message::Message,
     message_handler::MessageHandler,
     message_response::MessageResponse,
-    node_info::{NodeConnectionInfo, NodeInfo},
+    message_store::MessageStore,
+    node_id::{NodeId, NodeKind},
+    node_info::{ClientConnectionInfo, NodeConnectionInfo, NodeInfo},
     packet::Packet,
-    payload::Payload,
-    topology::{BroadcastTopology, Topology},
+    payload::{AddValue, Payload, ReadValue},
+    topology::{
+        BroadcastRedundancy, BroadcastTopology, DisconnectedTopologyPolicy, IndexedTopology,
+        Topology, TopologyStatus, TopologyStrategy,
+    },
+};
+
+pub mod services;
+pub use services::{
+    kv::{KvClient, KvReply, KvRequest},
+    lin_tso::{LinTsoClient, TsReply, TsRequest},
+};
+
+pub mod crdt;
+pub use crdt::{
+    g_counter::GCounter,
+    g_set::GSet,
+    lww_map::LwwMap,
+    lww_register::LwwRegister,
+    mv_register::MvRegister,
+    or_set::OrSet,
+    pn_counter::PnCounter,
+    replicator::{CrdtReplicator, ReplicationMode},
+    two_p_set::TwoPSet,
+    Crdt,
+};
+
+pub mod replication;
+pub use replication::{
+    handler::ReplicatedHandler, ClientRequest, Committed, Consensus, StateMachine,
+};
+
+pub mod raft;
+pub use raft::{
+    consensus::{Raft, RaftRole},
+    log::{LogEntry, RaftLog},
+    RaftHandler,
+};
+
+pub mod paxos;
+pub use paxos::{AcceptedValue, Ballot, MultiPaxos, PaxosHandler, PaxosRole};
+
+pub mod two_phase_commit;
+pub use two_phase_commit::{
+    coordinator::{Coordinator, PendingTxn},
+    participant::Participant,
+    Decision, Resource, TxnId,
+};
+
+pub mod txn;
+pub use txn::{
+    hlc::{HybridClock, Timestamp},
+    mvcc::{Commit, MvccStore, Transaction},
+    MicroOp,
 };
 
+pub mod sharding;
+pub use sharding::{HashRing, Sharded, ShardedHandler};
+
 pub mod handlers;
 pub use handlers::{
-    broadcast_handler::BroadcastHandler, echo_handler::EchoHandler,
+    broadcast_handler::{BroadcastHandler, BroadcastMode},
+    datomic_handler::{DatomicHandler, DatomicTxn},
+    echo_handler::EchoHandler,
+    g_set_handler::{GSetHandler, GSetMode},
     generate_handler::GenerateHandler,
+    lin_kv_handler::{KvStore, LinKvHandler, PaxosLinKvHandler},
+    pn_counter_handler::PnCounterHandler,
+    sharded_kv_handler::{KvHandler, ShardedKvHandler},
+    txn_list_append_handler::{CertifyingTxn, TxnListAppendHandler},
 };
 
 #[cfg(test)]
 mod test {
-    use crate::types::helpers::{build_broadcast_topology, is_lower_node_id};
-    use crate::{BroadcastTopology, Topology};
+    use crate::topology_analysis;
+    use crate::types::helpers::{
+        build_broadcast_topology, build_redundant_broadcast_topology, build_topology,
+        connected_components, repair_topology,
+    };
+    use crate::types::id_strategy::SNOWFLAKE_EPOCH;
+    use crate::types::node_info::MessageSyncStatus;
+    use crate::{
+        AddValue, Ballot, BroadcastHandler, BroadcastMode, BroadcastRedundancy, BroadcastTopology,
+        Clock, Collection, Coordinator, Crdt, CrdtReplicator, DatomicHandler, Decision,
+        DeliveryConfig, GSet, GSetHandler, GSetMode, GenerateHandler, HashRing, HybridClock,
+        IdGenerator, IdStrategy, IndexedTopology, KvHandler, KvStore, LinKvHandler, LinTsoClient,
+        LwwMap, LwwRegister, Message, MessageHandler, MessageResponse, MessageStore, MicroOp,
+        MultiPaxos, MvRegister, MvccStore, NodeConnectionInfo, NodeId, NodeInfo, NodeKind, OrSet,
+        Packet, PacketHandler, Participant, PaxosLinKvHandler, Payload, PnCounter,
+        PnCounterHandler, Raft, ReadValue, ReplicationMode, Resource, ShardedKvHandler,
+        StateMachine, Topology, TopologyStrategy, Transaction, TsReply, TwoPSet, TxnId,
+        TxnListAppendHandler, UniqueId,
+    };
+    use proptest::prelude::*;
+    use std::cell::Cell;
     use std::cmp::Ordering;
-    use std::collections::{HashMap, HashSet};
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::io::{Cursor, Read, Write};
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_node_id() {
+        let mut ids: Vec<NodeId> = ["seq-kv", "c1", "n10", "lin-kv", "n2", "n1"]
+            .map(NodeId::from)
+            .into();
+        ids.sort();
+        assert_eq!(ids, ["n1", "n2", "n10", "c1", "lin-kv", "seq-kv"]);
+        assert_eq!(NodeId::from("n1"), NodeId::from(String::from("n1")));
+        assert_ne!(NodeId::from("n1"), NodeId::from("n01"));
+        assert!(NodeId::from("n01") == "n01" && NodeId::from("n01") != "n1");
+        assert!(NodeId::from("n1") == "n1" && NodeId::from("n1") != "n01");
+        assert_eq!(NodeId::from("c0").to_string(), "c0");
+        assert_eq!(NodeId::from("lin-kv").to_string(), "lin-kv");
+
+        assert_eq!(NodeId::from("n12").kind(), NodeKind::Server(12));
+        assert_eq!(NodeId::from("c3").kind(), NodeKind::Client(3));
+        assert_eq!(NodeId::from("seq-kv").kind(), NodeKind::Service);
+        // NOTE: Node Ids that only start like Server or Client Nodes are not numbered.
+        for id in ["n", "c", "n-1", "node1", "n1a", "n99999999999999999999999"] {
+            assert_eq!(NodeId::from(id).kind(), NodeKind::Service, "{}", id);
+            assert_eq!(NodeId::from(id).number(), None);
+        }
+
+        let n1: NodeId = serde_json::from_str("\"n1\"").unwrap();
+        assert_eq!(n1, "n1");
+        assert_eq!(serde_json::to_string(&n1).unwrap(), "\"n1\"");
+        let topology: HashMap<NodeId, usize> = HashMap::from([(n1, 1)]);
+        assert_eq!(topology.get(&NodeId::from("n1")), Some(&1));
+    }
+
+    #[test]
+    fn test_message_sync_status() {
+        let mut status = MessageSyncStatus::Synced { last_msg_id: 0 };
+        assert_eq!(status.is_next_msg_id(1), Ordering::Equal);
+        status.increment_msg_id();
+
+        assert_eq!(status.is_next_msg_id(5), Ordering::Greater);
+        status.add_missing_msg_ids(5);
+        assert_eq!(status.get_missing_msg_ids(), vec![2, 3, 4]);
+        assert_eq!(status.get_next_msg_id(), 6);
+
+        assert!(status.remove_missing_msg_id(3));
+        assert!(!status.remove_missing_msg_id(3));
+        assert!(status.remove_missing_msg_id(2));
+        assert!(status.remove_missing_msg_id(4));
+        assert!(status.is_synced());
+        assert_eq!(status.is_next_msg_id(5), Ordering::Less);
+    }
+
+    struct AckHandler {}
+
+    impl MessageHandler for AckHandler {
+        fn handle_message(
+            &mut self,
+            packet: &Packet,
+            _state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            match packet.body.payload {
+                Payload::Echo { .. } => (0..3)
+                    .map(|_| MessageResponse::Ack {
+                        src: None,
+                        dest: NodeId::from("n1"),
+                        in_reply_to: None,
+                        payload: Payload::Generate,
+                    })
+                    .collect::<Vec<MessageResponse>>()
+                    .into(),
+                _ => Collection::None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_selective_resend() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"sync_request","msg_ids":[2]}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"ack","in_reply_to":2}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"sync_request","msg_ids":[2]}}"#,
+        ];
+        let mut ack_handler = AckHandler {};
+        let packets = run_packet_handler(&input, &mut ack_handler, Default::default());
+        let resent: Vec<&Message> = packets
+            .iter()
+            .skip(2)
+            .map(|p| &p.body)
+            .filter(|m| m.payload == Payload::Generate)
+            .collect();
+
+        // NOTE: Only the requested message is resent and it is not resent after its Ack.
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0].msg_id, Some(2));
+    }
+
+    fn run_packet_handler(
+        input: &[&str],
+        handler: &mut dyn MessageHandler,
+        delivery: DeliveryConfig,
+    ) -> Vec<Packet> {
+        run_ticking_packet_handler(input, handler, delivery, false)
+    }
+
+    /// NOTE: If tick is set, the PacketHandler is ticked after every Packet but the Init.
+    fn run_ticking_packet_handler(
+        input: &[&str],
+        handler: &mut dyn MessageHandler,
+        delivery: DeliveryConfig,
+        tick: bool,
+    ) -> Vec<Packet> {
+        let input = input.join("\n");
+        let lines = input.lines().count();
+        let mut output = Vec::new();
+
+        let mut packet_handler = PacketHandler::new(Cursor::new(input.into_bytes()), &mut output);
+        packet_handler.set_delivery_config(delivery);
+        packet_handler.add_handler(handler);
+        for line in 0..lines {
+            packet_handler.step();
+            if tick && line > 0 {
+                packet_handler.tick();
+            }
+        }
+        drop(packet_handler);
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    /// Answers with a Response on every tick, which has no received Packet to answer.
+    struct TickResponseHandler {}
+
+    impl MessageHandler for TickResponseHandler {
+        fn handle_message(
+            &mut self,
+            _packet: &Packet,
+            _state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            Collection::None
+        }
+        fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+            Collection::One(MessageResponse::Response {
+                payload: Payload::Ack,
+            })
+        }
+    }
+
+    #[test]
+    fn test_tick_response_is_dropped() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+        ];
+        let mut handler = TickResponseHandler {};
+        let packets =
+            run_ticking_packet_handler(&input, &mut handler, DeliveryConfig::default(), true);
+
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].body.payload, Payload::InitOk);
+    }
+
+    #[test]
+    fn test_resend_timeout() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"ack","in_reply_to":2}}"#,
+        ];
+        let mut delivery = DeliveryConfig::default();
+        delivery.set_resend_timeout(Duration::ZERO);
+        let mut ack_handler = AckHandler {};
+        let packets = run_ticking_packet_handler(&input, &mut ack_handler, delivery, true);
+        let sent: Vec<usize> = packets
+            .iter()
+            .filter(|p| p.dest == "n1")
+            .flat_map(|p| -> Vec<usize> {
+                match &p.body.payload {
+                    Payload::Batch { messages } => {
+                        messages.iter().filter_map(|m| m.msg_id).collect()
+                    }
+                    _ => p.body.msg_id.into_iter().collect(),
+                }
+            })
+            .collect();
+
+        // NOTE: Every tick resends the overdue Messages without waiting for a SyncRequest,
+        // until they are acknowledged.
+        let count = |msg_id: usize| sent.iter().filter(|id| **id == msg_id).count();
+        assert_eq!((count(1), count(2), count(3)), (3, 2, 3));
+    }
+
+    #[test]
+    fn test_exactly_once_client_requests() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"generate","msg_id":1}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"generate","msg_id":1}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"generate","msg_id":2}}"#,
+        ];
+        let mut generate_handler = GenerateHandler::new(IdStrategy::Striding {
+            seed_from_clock: false,
+        });
+        let packets = run_packet_handler(
+            &input,
+            &mut generate_handler,
+            DeliveryConfig::exactly_once(16),
+        );
+
+        assert_eq!(packets.len(), 4);
+        assert_eq!(packets[1], packets[2]);
+        assert_ne!(packets[1].body.payload, packets[3].body.payload);
+        assert_eq!(generate_handler.generator.counter, 2);
+    }
+
+    /// Answers every Echo with two EchoOks.
+    #[derive(Default)]
+    struct TwoRepliesHandler {
+        handled: usize,
+    }
+
+    impl MessageHandler for TwoRepliesHandler {
+        fn handle_message(
+            &mut self,
+            packet: &Packet,
+            _state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            match &packet.body.payload {
+                Payload::Echo { echo } => {
+                    self.handled += 1;
+                    (0..2)
+                        .map(|_| MessageResponse::NoAck {
+                            src: None,
+                            dest: packet.src,
+                            in_reply_to: packet.body.msg_id,
+                            payload: Payload::EchoOk { echo: echo.clone() },
+                        })
+                        .collect::<Vec<MessageResponse>>()
+                        .into()
+                }
+                _ => Collection::None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_exactly_once_multiple_responses() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":"a"}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":"a"}}"#,
+        ];
+        let mut handler = TwoRepliesHandler::default();
+        let packets = run_packet_handler(&input, &mut handler, DeliveryConfig::exactly_once(16));
+
+        // NOTE: Both responses are recorded and replayed, each in its own Packet.
+        assert_eq!(handler.handled, 1);
+        assert_eq!(packets.len(), 5);
+        assert_eq!(packets[1..3], packets[3..5]);
+        assert!(packets[1..]
+            .iter()
+            .all(|p| p.dest == "c1" && p.body.in_reply_to == Some(1)));
+    }
+
+    struct TestClock(Rc<Cell<u64>>);
+
+    impl Clock for TestClock {
+        fn now_millis(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_unique_ids_across_restarts() {
+        let strategies = [
+            IdStrategy::striding(),
+            IdStrategy::snowflake(),
+            IdStrategy::UuidV4,
+            IdStrategy::UuidV7,
+        ];
+
+        for strategy in strategies {
+            let now = Rc::new(Cell::new(SNOWFLAKE_EPOCH + 1_000_000));
+            let mut ids: HashSet<UniqueId> = HashSet::new();
+
+            for _ in 0..3 {
+                let mut generators: Vec<IdGenerator> = (0..3)
+                    .map(|_| {
+                        IdGenerator::with_clock(strategy.clone(), Box::new(TestClock(now.clone())))
+                    })
+                    .collect();
+                let mut last_ids: Vec<Option<UniqueId>> = vec![None; generators.len()];
+
+                for round in 0..6000 {
+                    // NOTE: The clock goes backwards once per run.
+                    match round {
+                        3000 => now.set(now.get() - 5),
+                        round if round % 100 == 0 => now.set(now.get() + 1),
+                        _ => {}
+                    }
+
+                    for (node, generator) in generators.iter_mut().enumerate() {
+                        let id = generator.next_id(node, 3);
+                        match (&strategy, &id) {
+                            (IdStrategy::UuidV4, UniqueId::Uuid(uuid)) => {
+                                assert_eq!(&uuid[14..15], "4")
+                            }
+                            (IdStrategy::UuidV7, UniqueId::Uuid(uuid)) => {
+                                assert_eq!(&uuid[14..15], "7");
+                                // NOTE: UUIDv7 of one Node are ordered despite the clock.
+                                if let Some(UniqueId::Uuid(last)) = &last_ids[node] {
+                                    assert!(uuid[..18] > last[..18], "{} <= {}", uuid, last);
+                                }
+                            }
+                            (_, UniqueId::Number(id)) => {
+                                if let Some(UniqueId::Number(last)) = &last_ids[node] {
+                                    assert!(id > last, "{:?}: {} <= {}", strategy, id, last);
+                                }
+                            }
+                            _ => panic!("{:?} generated {:?}", strategy, id),
+                        }
+                        assert!(
+                            ids.insert(id.clone()),
+                            "{:?} generated {:?} twice",
+                            strategy,
+                            id
+                        );
+                        last_ids[node] = Some(id);
+                    }
+                }
+
+                // NOTE: All Nodes crash and restart a second later with a fresh state.
+                now.set(now.get() + 1000);
+            }
+
+            assert_eq!(ids.len(), 3 * 3 * 6000);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Node number 4 does not fit into 2 bits")]
+    fn test_snowflake_node_bits() {
+        let mut generator = IdGenerator::new(IdStrategy::Snowflake {
+            epoch: SNOWFLAKE_EPOCH,
+            node_bits: 2,
+            sequence_bits: 12,
+        });
+        generator.next_id(3, 5);
+        generator.next_id(4, 5);
+    }
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        msg_ids: Vec<usize>,
+    }
+
+    impl MessageHandler for RecordingHandler {
+        fn handle_message(
+            &mut self,
+            packet: &Packet,
+            _state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            if let Payload::Generate = packet.body.payload {
+                self.msg_ids.extend(packet.body.msg_id);
+            }
+            Collection::None
+        }
+    }
+
+    #[test]
+    fn test_fifo_delivery() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":1}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":3}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":4}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":3}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":2}}"#,
+        ];
+
+        let mut handler = RecordingHandler::default();
+        run_packet_handler(&input, &mut handler, DeliveryConfig::fifo());
+        assert_eq!(handler.msg_ids, vec![1, 2, 3, 4]);
+
+        let mut handler = RecordingHandler::default();
+        run_packet_handler(&input, &mut handler, Default::default());
+        assert_eq!(handler.msg_ids, vec![1, 3, 4, 2]);
+    }
+
+    #[test]
+    fn test_exactly_once_reorder_buffer() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":1}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":4}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":3}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":6}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":4}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":2}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":5}}"#,
+            r#"{"src":"n1","dest":"n0","body":{"type":"generate","msg_id":7}}"#,
+        ];
+
+        let mut handler = RecordingHandler::default();
+        run_packet_handler(&input, &mut handler, DeliveryConfig::exactly_once(16));
+        assert_eq!(handler.msg_ids, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    /// Answers an Echo only once a Generate has been received.
+    #[derive(Default)]
+    struct DeferringHandler {
+        echoes: Vec<Packet>,
+    }
+
+    impl MessageHandler for DeferringHandler {
+        fn handle_message(
+            &mut self,
+            packet: &Packet,
+            _state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            match &packet.body.payload {
+                Payload::Echo { .. } => {
+                    self.echoes.push(packet.clone());
+                    Collection::None
+                }
+                Payload::Generate => self
+                    .echoes
+                    .iter()
+                    .map(|echo| MessageResponse::NoAck {
+                        src: None,
+                        dest: echo.src,
+                        in_reply_to: echo.body.msg_id,
+                        payload: Payload::EchoOk {
+                            echo: String::new(),
+                        },
+                    })
+                    .collect::<Vec<MessageResponse>>()
+                    .into(),
+                _ => Collection::None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_exactly_once_retry_in_progress() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+            r#"{"src":"c2","dest":"n0","body":{"type":"generate","msg_id":1}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"echo","msg_id":1,"echo":""}}"#,
+        ];
+        let mut handler = DeferringHandler::default();
+        let packets = run_packet_handler(&input, &mut handler, DeliveryConfig::exactly_once(16));
+
+        // NOTE: The retry that arrives before the response is dropped, the later one replays
+        // the response.
+        assert_eq!(handler.echoes.len(), 1);
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[1], packets[2]);
+        assert!(packets[1].dest == "c1" && packets[1].body.in_reply_to == Some(1));
+    }
+
+    fn create_node_info(node_id: &str, server_nodes: &[&str], topology: Topology) -> NodeInfo {
+        let server_nodes: Vec<NodeId> = server_nodes.iter().map(|n| NodeId::from(*n)).collect();
+        NodeInfo {
+            node_id: NodeId::from(node_id),
+            client_nodes: vec![],
+            server_nodes: server_nodes.clone(),
+            node_ids: server_nodes.clone(),
+            indexed_topology: IndexedTopology::new(&server_nodes, &topology),
+            topology,
+            topology_status: Default::default(),
+            broadcast_topology: Default::default(),
+            suspected_nodes: Default::default(),
+            node_number: node_id[1..].parse().unwrap(),
+            msg_number: 0,
+            conn_info: server_nodes
+                .iter()
+                .map(|n| (*n, NodeConnectionInfo::default()))
+                .collect(),
+            client_conn_info: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_broadcast_gossip() {
+        let state = create_node_info("n0", &["n0", "n1", "n2"], Default::default());
+        let mut handler = BroadcastHandler::new(BroadcastMode::Gossip {
+            interval: Duration::ZERO,
+            fanout: 1,
+        });
+        handler.messages.extend(&[1, 2]);
+
+        let gossip = |messages: Vec<usize>, up_to, received| Packet {
+            src: NodeId::from("n1"),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: None,
+                in_reply_to: None,
+                payload: Payload::Gossip {
+                    messages,
+                    up_to,
+                    received,
+                },
+            },
+        };
+        let gossip_ok = |handler: &mut BroadcastHandler, gossip: &Packet| -> Payload {
+            match handler.handle_message(gossip, &state) {
+                Collection::One(MessageResponse::NoAck { dest, payload, .. }) => {
+                    assert_eq!(dest, "n1");
+                    payload
+                }
+                _ => panic!("Expected a single GossipOk"),
+            }
+        };
+        assert_eq!(
+            gossip_ok(&mut handler, &gossip(vec![2, 3], 2, 0)),
+            Payload::GossipOk {
+                messages: vec![1],
+                up_to: 3,
+                received: 2
+            }
+        );
+        assert_eq!(handler.messages.snapshot(), vec![1, 2, 3]);
+
+        // NOTE: Once n1 has acknowledged the messages, they are not sent to it again.
+        assert_eq!(
+            gossip_ok(&mut handler, &gossip(vec![], 2, 3)),
+            Payload::GossipOk {
+                messages: vec![],
+                up_to: 3,
+                received: 2
+            }
+        );
+
+        match handler.handle_tick(&state) {
+            Collection::One(MessageResponse::NoAck { dest, payload, .. }) => {
+                let expected = match dest == "n1" {
+                    true => (vec![], 2),
+                    false => (vec![1, 2, 3], 0),
+                };
+                assert_eq!(
+                    payload,
+                    Payload::Gossip {
+                        messages: expected.0,
+                        up_to: 3,
+                        received: expected.1
+                    }
+                );
+            }
+            _ => panic!("Expected a single Gossip"),
+        }
+    }
+
+    #[test]
+    fn test_pn_counter() {
+        let nodes = ["n0", "n1", "n2"];
+        let topology: Topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (
+                NodeId::from("n1"),
+                vec![NodeId::from("n0"), NodeId::from("n2")],
+            ),
+            (NodeId::from("n2"), vec![NodeId::from("n1")]),
+        ]);
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, topology.clone()))
+            .collect();
+        let mut handlers: Vec<PnCounterHandler> = nodes
+            .iter()
+            .map(|_| PnCounterHandler::new(Duration::ZERO))
+            .collect();
+
+        let request = |payload: Payload| Packet {
+            src: NodeId::from("c1"),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: Some(1),
+                in_reply_to: None,
+                payload,
+            },
+        };
+        let read = |handler: &mut PnCounterHandler, state: &NodeInfo| match handler
+            .handle_message(&request(Payload::Read { key: None }), state)
+        {
+            Collection::One(MessageResponse::NoAck {
+                payload: Payload::ReadOk(ReadValue::Counter { value }),
+                ..
+            }) => value,
+            _ => panic!("Expected a single ReadOk"),
+        };
+        // NOTE: Sends the counters of all Nodes to their neighbours and delivers the
+        // acknowledgements, except over the failed link.
+        let gossip = |handlers: &mut Vec<PnCounterHandler>, failed_link: Option<(usize, usize)>| {
+            let mut queue: Vec<(usize, MessageResponse)> = Vec::new();
+            for src in 0..handlers.len() {
+                let responses: Vec<MessageResponse> =
+                    handlers[src].handle_tick(&states[src]).into();
+                queue.extend(responses.into_iter().map(|r| (src, r)));
+            }
+            let failed = |src: usize, dest: &NodeId, _: &Payload| {
+                let dest = nodes.iter().position(|n| *dest == *n).unwrap();
+                failed_link.is_some_and(|l| l == (src, dest) || l == (dest, src))
+            };
+            deliver_filtered(handlers, &states, queue, &failed, &mut no_service);
+        };
+
+        let add: Payload = serde_json::from_str(r#"{"type":"add","delta":-3}"#).unwrap();
+        assert_eq!(add, Payload::Add(AddValue::Delta { delta: -3 }));
+        handlers[1].handle_message(&request(add), &states[1]);
+        handlers[0].handle_message(
+            &request(Payload::Add(AddValue::Delta { delta: 5 })),
+            &states[0],
+        );
+
+        // NOTE: n2 is partitioned from the other Nodes.
+        for _ in 0..3 {
+            gossip(&mut handlers, Some((1, 2)));
+        }
+        handlers[2].handle_message(
+            &request(Payload::Add(AddValue::Delta { delta: 10 })),
+            &states[2],
+        );
+        handlers[2].handle_message(
+            &request(Payload::Add(AddValue::Delta { delta: -1 })),
+            &states[2],
+        );
+        assert_eq!(read(&mut handlers[0], &states[0]), 2);
+        assert_eq!(read(&mut handlers[2], &states[2]), 9);
+
+        // NOTE: The partition heals.
+        for _ in 0..2 {
+            gossip(&mut handlers, None);
+        }
+        for (handler, state) in handlers.iter_mut().zip(states.iter()) {
+            assert_eq!(read(handler, state), 11);
+        }
+
+        // NOTE: Every Node knows that its neighbours have the whole counter, so there are no
+        // more deltas to send.
+        for (handler, state) in handlers.iter_mut().zip(states.iter()) {
+            assert!(matches!(handler.handle_tick(state), Collection::None));
+        }
+
+        let state = Payload::CrdtState {
+            name: String::from("pn-counter"),
+            state: serde_json::to_value(&handlers[0].counter.crdt).unwrap(),
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<Payload>(&json).unwrap(), state);
+        assert_eq!(
+            serde_json::to_string(&Payload::ReadOk(ReadValue::Counter { value: 11 })).unwrap(),
+            r#"{"type":"read_ok","value":11}"#
+        );
+    }
+
+    /// Checks that merging the replicas is commutative, associative and idempotent and that the
+    /// deltas between them result in the same state as merging the whole state.
+    fn check_crdt_laws<T: Crdt + std::fmt::Debug>(replicas: &[T]) {
+        let merged = |a: &T, b: &T| {
+            let mut a = a.clone();
+            a.merge(b);
+            a
+        };
+        for a in replicas {
+            assert_eq!(merged(a, a), *a);
+            assert!(!a.clone().merge(a));
+            for b in replicas {
+                assert_eq!(merged(a, b), merged(b, a));
+                assert_eq!(merged(b, &a.delta_since(b)), merged(b, a));
+                assert_eq!(merged(a, b).delta_since(&merged(a, b)), T::default());
+                for c in replicas {
+                    assert_eq!(merged(&merged(a, b), c), merged(a, &merged(b, c)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crdts() {
+        let (n1, n2, n3) = (NodeId::from("n1"), NodeId::from("n2"), NodeId::from("n3"));
+
+        let mut counters = vec![PnCounter::default(); 3];
+        counters[0].add(n1, 5);
+        counters[1].add(n2, -3);
+        counters[2] = counters[0].clone();
+        counters[2].add(n3, 2);
+        counters[2].add(n1, -1);
+        check_crdt_laws(&counters);
+        let mut counter = counters[0].clone();
+        counters.iter().for_each(|c| _ = counter.merge(c));
+        assert_eq!(counter.value(), 3);
+
+        let mut g_sets = vec![GSet::default(); 3];
+        g_sets[0].add(1);
+        g_sets[1].add(2);
+        g_sets[2].add(1);
+        g_sets[2].add(3);
+        check_crdt_laws(&g_sets);
+
+        // NOTE: Removed elements can not be added again.
+        let mut two_p_sets = vec![TwoPSet::default(); 3];
+        two_p_sets[0].add(1);
+        two_p_sets[1] = two_p_sets[0].clone();
+        assert!(two_p_sets[1].remove(1));
+        assert!(!two_p_sets[1].add(1));
+        two_p_sets[2].add(2);
+        check_crdt_laws(&two_p_sets);
+        let mut two_p_set = two_p_sets[0].clone();
+        two_p_sets.iter().for_each(|s| _ = two_p_set.merge(s));
+        assert_eq!(two_p_set.value(), BTreeSet::from([2]));
+
+        // NOTE: Concurrent adds win over removes.
+        let mut or_sets = vec![OrSet::default(); 3];
+        or_sets[0].add(n1, 1);
+        or_sets[1] = or_sets[0].clone();
+        assert!(or_sets[1].remove(&1));
+        or_sets[2] = or_sets[0].clone();
+        or_sets[2].add(n3, 1);
+        or_sets[2].add(n3, 2);
+        check_crdt_laws(&or_sets);
+        let mut or_set = or_sets[0].clone();
+        or_set.merge(&or_sets[1]);
+        assert!(!or_set.contains(&1));
+        or_set.merge(&or_sets[2]);
+        assert_eq!(or_set.value(), BTreeSet::from([1, 2]));
+
+        let mut registers = vec![LwwRegister::default(); 3];
+        registers[0].set(1, 10, n1);
+        registers[1].set(2, 10, n2);
+        registers[2] = registers[0].clone();
+        registers[2].set(3, 5, n3);
+        check_crdt_laws(&registers);
+        let mut register = registers[0].clone();
+        registers.iter().for_each(|r| _ = register.merge(r));
+        assert_eq!(register.value(), Some(3));
+        assert_eq!(register.timestamp, 11);
+
+        let mut mv_registers = vec![MvRegister::default(); 3];
+        mv_registers[0].set(n1, 1);
+        mv_registers[1] = mv_registers[0].clone();
+        mv_registers[1].set(n2, 2);
+        mv_registers[2] = mv_registers[0].clone();
+        mv_registers[2].set(n3, 3);
+        check_crdt_laws(&mv_registers);
+        let mut mv_register = mv_registers[0].clone();
+        mv_registers.iter().for_each(|r| _ = mv_register.merge(r));
+        assert_eq!(mv_register.value(), vec![2, 3]);
+        mv_register.set(n1, 4);
+        assert!(!mv_register.clone().merge(&mv_registers[2]));
+        assert_eq!(mv_register.value(), vec![4]);
+
+        let mut maps = vec![LwwMap::default(); 3];
+        maps[0].insert(String::from("x"), 1, 1, n1);
+        maps[0].insert(String::from("y"), 2, 2, n1);
+        maps[1] = maps[0].clone();
+        maps[1].remove(String::from("x"), 3, n2);
+        maps[2].insert(String::from("y"), 3, 1, n3);
+        check_crdt_laws(&maps);
+        let mut map = maps[0].clone();
+        maps.iter().for_each(|m| _ = map.merge(m));
+        assert_eq!(map.value(), BTreeMap::from([(String::from("y"), 2)]));
+        assert_eq!(map.delta_since(&maps[1]), maps[1].delta_since(&maps[1]));
+        let json = serde_json::to_value(&map).unwrap();
+        assert_eq!(
+            serde_json::from_value::<LwwMap<String, i32>>(json).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn test_crdt_replicator() {
+        let nodes = ["n0", "n1"];
+        let topology: Topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (NodeId::from("n1"), vec![NodeId::from("n0")]),
+        ]);
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, topology.clone()))
+            .collect();
+        let mut replicators: Vec<CrdtReplicator<OrSet<usize>>> = nodes
+            .iter()
+            .map(|_| CrdtReplicator::new("set", ReplicationMode::Delta, Duration::ZERO))
+            .collect();
+
+        // NOTE: Holds back the Messages to n0, so the acknowledgement can be delivered later.
+        let to_n0 = |_: usize, dest: &NodeId, _: &Payload| *dest == "n0";
+        let never = |_: usize, _: &NodeId, _: &Payload| false;
+
+        replicators[0].crdt.add(states[0].node_id, 1);
+        replicators[0].crdt.add(states[0].node_id, 2);
+        let delta: Vec<MessageResponse> = replicators[0].handle_tick(&states[0]).into();
+        assert_eq!(delta.len(), 1);
+        let queue = delta.into_iter().map(|r| (0, r)).collect();
+        let delivery = deliver_filtered(&mut replicators, &states, queue, &to_n0, &mut no_service);
+        assert!(matches!(
+            delivery.held.as_slice(),
+            [(
+                1,
+                MessageResponse::NoAck {
+                    payload: Payload::CrdtStateOk { .. },
+                    ..
+                }
+            )]
+        ));
+        assert_eq!(replicators[1].crdt.value(), BTreeSet::from([1, 2]));
+
+        // NOTE: The state received from n0 is known to n0, so it is never sent back to it.
+        assert!(matches!(
+            replicators[1].handle_tick(&states[1]),
+            Collection::None
+        ));
+        deliver_filtered(
+            &mut replicators,
+            &states,
+            delivery.held,
+            &never,
+            &mut no_service,
+        );
+
+        // NOTE: Only the removal is sent after the acknowledgement.
+        replicators[0].crdt.remove(&1);
+        match replicators[0].handle_tick(&states[0]) {
+            Collection::One(MessageResponse::NoAck {
+                payload: Payload::CrdtState { name, state },
+                ..
+            }) => {
+                assert_eq!(name, "set");
+                let delta: OrSet<usize> = serde_json::from_value(state).unwrap();
+                assert!(delta.adds.is_empty());
+                assert_eq!(delta.removed.len(), 1);
+            }
+            _ => panic!("Expected a single CrdtState"),
+        }
+
+        // NOTE: States of other Crdts are ignored.
+        let other = Payload::CrdtState {
+            name: String::from("other"),
+            state: serde_json::json!({"adds": [], "removed": []}),
+        };
+        let response = MessageResponse::NoAck {
+            src: None,
+            dest: states[1].node_id,
+            in_reply_to: None,
+            payload: other,
+        };
+        let delivery = deliver_filtered(
+            &mut replicators,
+            &states,
+            vec![(0, response)],
+            &never,
+            &mut no_service,
+        );
+        assert_eq!(delivery.delivered, 1);
+    }
+
+    #[test]
+    fn test_g_set() {
+        let nodes = ["n0", "n1", "n2"];
+        let topology: Topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (
+                NodeId::from("n1"),
+                vec![NodeId::from("n0"), NodeId::from("n2")],
+            ),
+            (NodeId::from("n2"), vec![NodeId::from("n1")]),
+        ]);
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, topology.clone()))
+            .collect();
+
+        let request = |payload: Payload| Packet {
+            src: NodeId::from("c1"),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: Some(1),
+                in_reply_to: None,
+                payload,
+            },
+        };
+        let read = |handler: &mut GSetHandler, state: &NodeInfo| match handler
+            .handle_message(&request(Payload::Read { key: None }), state)
+        {
+            Collection::One(MessageResponse::NoAck {
+                payload: Payload::ReadOk(ReadValue::Elements { value }),
+                ..
+            }) => value,
+            _ => panic!("Expected a single ReadOk"),
+        };
+        // NOTE: Delivers the responses between the Nodes until there are none left and returns
+        // the number of delivered Messages.
+        let deliver = |handlers: &mut Vec<GSetHandler>, queue: Vec<(usize, MessageResponse)>| {
+            let never = |_: usize, _: &NodeId, _: &Payload| false;
+            deliver_filtered(handlers, &states, queue, &never, &mut no_service).delivered
+        };
+
+        let add: Payload = serde_json::from_str(r#"{"type":"add","element":3}"#).unwrap();
+        assert_eq!(add, Payload::Add(AddValue::Element { element: 3 }));
+
+        // NOTE: Every new element is forwarded once along the line.
+        let mut handlers: Vec<GSetHandler> = nodes
+            .iter()
+            .map(|_| GSetHandler::new(GSetMode::Operation))
+            .collect();
+        for (node, element, forwarded) in [(0, 3, 2), (2, 1, 2), (1, 3, 0)] {
+            let add = Payload::Add(AddValue::Element { element });
+            let responses: Vec<MessageResponse> = handlers[node]
+                .handle_message(&request(add), &states[node])
+                .into();
+            let queue = responses.into_iter().map(|r| (node, r)).collect();
+            assert_eq!(deliver(&mut handlers, queue), forwarded);
+        }
+        for (handler, state) in handlers.iter_mut().zip(states.iter()) {
+            assert_eq!(read(handler, state), vec![1, 3]);
+            assert!(matches!(handler.handle_tick(state), Collection::None));
+        }
+
+        let mut handlers: Vec<GSetHandler> = nodes
+            .iter()
+            .map(|_| {
+                GSetHandler::new(GSetMode::State {
+                    replication: ReplicationMode::Delta,
+                    interval: Duration::ZERO,
+                })
+            })
+            .collect();
+        handlers[0].handle_message(&request(add), &states[0]);
+        handlers[2].handle_message(
+            &request(Payload::Add(AddValue::Element { element: -1 })),
+            &states[2],
+        );
+        assert_eq!(read(&mut handlers[0], &states[0]), vec![3]);
+        for _ in 0..2 {
+            let mut queue = Vec::new();
+            for (node, (handler, state)) in handlers.iter_mut().zip(states.iter()).enumerate() {
+                let responses: Vec<MessageResponse> = handler.handle_tick(state).into();
+                queue.extend(responses.into_iter().map(|r| (node, r)));
+            }
+            deliver(&mut handlers, queue);
+        }
+        for (handler, state) in handlers.iter_mut().zip(states.iter()) {
+            assert_eq!(read(handler, state), vec![-1, 3]);
+        }
+
+        assert_eq!(
+            serde_json::to_string(&Payload::ReadOk(ReadValue::Elements { value: vec![-1, 3] }))
+                .unwrap(),
+            r#"{"type":"read_ok","value":[-1,3]}"#
+        );
+    }
+
+    /// NOTE: Allows delivering Messages between handlers of different types (e.g. the
+    /// coordinator and the participants of two-phase commits).
+    impl<T: MessageHandler + ?Sized> MessageHandler for &mut T {
+        fn handle_message(
+            &mut self,
+            packet: &Packet,
+            state: &NodeInfo,
+        ) -> Collection<MessageResponse> {
+            (**self).handle_message(packet, state)
+        }
+        fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+            (**self).handle_tick(state)
+        }
+    }
+
+    /// Messages delivered by deliver_filtered.
+    #[derive(Debug, Default)]
+    struct Delivery {
+        /// Messages sent to Clients.
+        replies: Vec<(NodeId, Option<usize>, Payload)>,
+        /// Messages that have been held back, by their source Node.
+        held: Vec<(usize, MessageResponse)>,
+        /// Number of Messages delivered to Nodes.
+        delivered: usize,
+    }
+
+    /// Delivers the Messages between the Nodes (unwrapping Forwards like the PacketHandler)
+    /// until there are none left. Messages for which hold returns true (given the source Node,
+    /// the destination and the Payload) are held back and Requests to Services are answered
+    /// by service.
+    fn deliver_filtered<H: MessageHandler>(
+        handlers: &mut [H],
+        states: &[NodeInfo],
+        mut queue: Vec<(usize, MessageResponse)>,
+        hold: &dyn Fn(usize, &NodeId, &Payload) -> bool,
+        service: &mut dyn FnMut(&NodeId, &Payload) -> Payload,
+    ) -> Delivery {
+        let mut delivery = Delivery::default();
+        while let Some((src, response)) = queue.pop() {
+            let (MessageResponse::NoAck { dest, payload, .. }
+            | MessageResponse::Ack { dest, payload, .. }
+            | MessageResponse::Request { dest, payload, .. }) = &response
+            else {
+                panic!("Expected a Message to another Node, got {:?}", response);
+            };
+            if hold(src, dest, payload) {
+                delivery.held.push((src, response));
+                continue;
+            }
+            let (src, dest, in_reply_to, payload) = match response {
+                MessageResponse::NoAck {
+                    dest,
+                    in_reply_to,
+                    payload,
+                    ..
+                }
+                | MessageResponse::Ack {
+                    dest,
+                    in_reply_to,
+                    payload,
+                    ..
+                } => (states[src].node_id, dest, in_reply_to, payload),
+                MessageResponse::Request {
+                    dest,
+                    msg_id,
+                    payload,
+                } => (
+                    dest,
+                    states[src].node_id,
+                    Some(msg_id),
+                    service(&dest, &payload),
+                ),
+                _ => unreachable!(),
+            };
+            let Some(node) = states.iter().position(|s| s.node_id == dest) else {
+                delivery.replies.push((dest, in_reply_to, payload));
+                continue;
+            };
+            let packet = match payload {
+                Payload::Forward { packet } => *packet,
+                payload => Packet {
+                    src,
+                    dest,
+                    body: Message {
+                        msg_id: None,
+                        in_reply_to,
+                        payload,
+                    },
+                },
+            };
+            delivery.delivered += 1;
+            let responses: Vec<MessageResponse> =
+                handlers[node].handle_message(&packet, &states[node]).into();
+            queue.extend(responses.into_iter().map(|r| (node, r)));
+        }
+        delivery
+    }
+
+    /// Delivers the Messages between the Nodes and returns the Messages sent to Clients,
+    /// except Messages to the isolated Node.
+    fn deliver_messages<H: MessageHandler>(
+        handlers: &mut [H],
+        states: &[NodeInfo],
+        queue: Vec<(usize, MessageResponse)>,
+        isolated: Option<usize>,
+    ) -> Vec<(NodeId, Option<usize>, Payload)> {
+        let hold = |src: usize, dest: &NodeId, _: &Payload| {
+            isolated.is_some_and(|i| states[i].node_id == *dest || (i == src && dest.is_server()))
+        };
+        deliver_filtered(handlers, states, queue, &hold, &mut no_service).replies
+    }
+
+    fn no_service(dest: &NodeId, payload: &Payload) -> Payload {
+        panic!("Unexpected {:?} to {}", payload, dest)
+    }
+
+    /// Sends a request from the Client "c1" to the Node and returns the single reply.
+    fn send_request<H: MessageHandler>(
+        handlers: &mut [H],
+        states: &[NodeInfo],
+        node: usize,
+        msg_id: usize,
+        payload: Payload,
+    ) -> Payload {
+        let packet = Packet {
+            src: NodeId::from("c1"),
+            dest: states[node].node_id,
+            body: Message {
+                msg_id: Some(msg_id),
+                in_reply_to: None,
+                payload,
+            },
+        };
+        let responses: Vec<MessageResponse> =
+            handlers[node].handle_message(&packet, &states[node]).into();
+        let queue = responses.into_iter().map(|r| (node, r)).collect();
+        match deliver_messages(handlers, states, queue, None).as_slice() {
+            [(client, in_reply_to, payload)] => {
+                assert!(*client == "c1");
+                assert_eq!(*in_reply_to, Some(msg_id));
+                payload.clone()
+            }
+            replies => panic!("Expected a single reply, got {:?}", replies),
+        }
+    }
+
+    #[test]
+    fn test_lin_kv() {
+        let nodes = ["n0", "n1", "n2"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let mut handlers: Vec<LinKvHandler> = nodes
+            .iter()
+            .map(|_| {
+                LinKvHandler::new(
+                    Raft::new(Duration::from_secs(60), Duration::ZERO),
+                    KvStore::default(),
+                )
+            })
+            .collect();
+
+        let read = |key: i64| Payload::Read {
+            key: Some(key.into()),
+        };
+
+        // NOTE: There is no leader before the first election.
+        assert!(matches!(
+            send_request(&mut handlers, &states, 1, 1, read(1)),
+            Payload::Error { code: 11, .. }
+        ));
+        handlers[0].consensus.election_deadline = Instant::now();
+        let responses: Vec<MessageResponse> = handlers[0].handle_tick(&states[0]).into();
+        deliver_messages(
+            &mut handlers,
+            &states,
+            responses.into_iter().map(|r| (0, r)).collect(),
+            None,
+        );
+        assert!(handlers[0].consensus.is_leader());
+        assert!(handlers[1..]
+            .iter()
+            .all(|h| h.consensus.leader == Some(states[0].node_id)));
+
+        let write: Payload = serde_json::from_str(r#"{"type":"write","key":1,"value":5}"#).unwrap();
+        assert_eq!(
+            send_request(&mut handlers, &states, 0, 2, write),
+            Payload::WriteOk
+        );
+        let cas = |from: i64, to: i64| Payload::Cas {
+            key: 1.into(),
+            from: from.into(),
+            to: to.into(),
+            create_if_not_exists: false,
+        };
+        // NOTE: Requests to followers are forwarded to the leader.
+        assert!(matches!(
+            send_request(&mut handlers, &states, 1, 3, cas(4, 6)),
+            Payload::Error { code: 22, .. }
+        ));
+        assert_eq!(
+            send_request(&mut handlers, &states, 2, 4, cas(5, 6)),
+            Payload::CasOk
+        );
+        // NOTE: Retried requests are answered without applying them again.
+        assert_eq!(
+            send_request(&mut handlers, &states, 1, 4, cas(5, 6)),
+            Payload::CasOk
+        );
+        assert_eq!(
+            send_request(&mut handlers, &states, 1, 5, read(1)),
+            Payload::ReadOk(ReadValue::Value { value: 6.into() })
+        );
+        assert!(matches!(
+            send_request(&mut handlers, &states, 2, 6, read(2)),
+            Payload::Error { code: 20, .. }
+        ));
+
+        // NOTE: Replies of the key-value services are deserialized as Value, whatever they hold.
+        for value in ["5", "[1,2]", r#"{"1":2}"#] {
+            let reply: Payload =
+                serde_json::from_str(&format!(r#"{{"type":"read_ok","value":{}}}"#, value))
+                    .unwrap();
+            let value: serde_json::Value = serde_json::from_str(value).unwrap();
+            assert_eq!(reply, Payload::ReadOk(ReadValue::Value { value }));
+        }
+
+        // NOTE: The followers apply the committed requests as well.
+        let responses: Vec<MessageResponse> = handlers[0].handle_tick(&states[0]).into();
+        deliver_messages(
+            &mut handlers,
+            &states,
+            responses.into_iter().map(|r| (0, r)).collect(),
+            None,
+        );
+        for handler in handlers.iter() {
+            assert_eq!(handler.state_machine.store.get("1"), Some(&6.into()));
+            assert_eq!(handler.consensus.log, handlers[0].consensus.log);
+        }
+
+        // NOTE: n0 is partitioned, n1 is elected and overwrites the uncommitted entry of n0.
+        let responses: Vec<MessageResponse> = handlers[0]
+            .handle_message(
+                &Packet {
+                    src: NodeId::from("c1"),
+                    dest: states[0].node_id,
+                    body: Message {
+                        msg_id: Some(7),
+                        in_reply_to: None,
+                        payload: cas(6, 7),
+                    },
+                },
+                &states[0],
+            )
+            .into();
+        deliver_messages(
+            &mut handlers,
+            &states,
+            responses.into_iter().map(|r| (0, r)).collect(),
+            Some(0),
+        );
+        handlers[1].consensus.election_deadline = Instant::now();
+        let responses: Vec<MessageResponse> = handlers[1].handle_tick(&states[1]).into();
+        deliver_messages(
+            &mut handlers,
+            &states,
+            responses.into_iter().map(|r| (1, r)).collect(),
+            Some(0),
+        );
+        assert!(handlers[1].consensus.is_leader());
+        assert_eq!(
+            send_request(&mut handlers, &states, 2, 8, cas(6, 8)),
+            Payload::CasOk
+        );
+
+        let responses: Vec<MessageResponse> = handlers[1].handle_tick(&states[1]).into();
+        assert!(deliver_messages(
+            &mut handlers,
+            &states,
+            responses.into_iter().map(|r| (1, r)).collect(),
+            None
+        )
+        .is_empty());
+        assert!(!handlers[0].consensus.is_leader());
+        for handler in handlers.iter() {
+            assert_eq!(handler.state_machine.store.get("1"), Some(&8.into()));
+            assert_eq!(handler.consensus.log, handlers[1].consensus.log);
+        }
+
+        // NOTE: n2 is partitioned while the log of the other Nodes is compacted, so it
+        // receives the snapshot once the partition heals.
+        for handler in handlers.iter_mut() {
+            handler.set_snapshot_threshold(2);
+        }
+        for msg_id in 10..15 {
+            let packet = Packet {
+                src: NodeId::from("c1"),
+                dest: states[1].node_id,
+                body: Message {
+                    msg_id: Some(msg_id),
+                    in_reply_to: None,
+                    payload: Payload::Write {
+                        key: (msg_id as i64).into(),
+                        value: 1.into(),
+                    },
+                },
+            };
+            let responses: Vec<MessageResponse> =
+                handlers[1].handle_message(&packet, &states[1]).into();
+            let queue = responses.into_iter().map(|r| (1, r)).collect();
+            assert_eq!(
+                deliver_messages(&mut handlers, &states, queue, Some(2)).len(),
+                1
+            );
+        }
+        assert!(handlers[1].consensus.log.snapshot_index > handlers[2].consensus.last_applied);
+        assert!(handlers[1].consensus.log.entries.len() <= 2);
+        for _ in 0..2 {
+            let responses: Vec<MessageResponse> = handlers[1].handle_tick(&states[1]).into();
+            deliver_messages(
+                &mut handlers,
+                &states,
+                responses.into_iter().map(|r| (1, r)).collect(),
+                None,
+            );
+        }
+        assert!(handlers[2].consensus.log.snapshot_index > 0);
+        assert_eq!(handlers[2].state_machine, handlers[1].state_machine);
+        assert_eq!(handlers[2].last_requests, handlers[1].last_requests);
+        assert_eq!(
+            handlers[2].consensus.commit_index,
+            handlers[1].consensus.commit_index
+        );
+
+        // NOTE: Replies to proxied requests are relayed through the proxy.
+        let input = [
+            r#"{"src":"c0","dest":"n1","body":{"type":"init","msg_id":1,"node_id":"n1","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"n0","dest":"n1","body":{"type":"forward","packet":{"src":"c1","dest":"n1","body":{"type":"generate","msg_id":7}}}}"#,
+            r#"{"src":"n0","dest":"n1","body":{"type":"forward","packet":{"src":"n1","dest":"c2","body":{"type":"write_ok","in_reply_to":3}}}}"#,
+        ];
+        let mut generate_handler = GenerateHandler::new(IdStrategy::Striding {
+            seed_from_clock: false,
+        });
+        let packets = run_packet_handler(&input, &mut generate_handler, DeliveryConfig::default());
+        match &packets[1] {
+            Packet {
+                src,
+                dest,
+                body:
+                    Message {
+                        payload: Payload::Forward { packet },
+                        ..
+                    },
+            } => {
+                assert!(*src == "n1" && *dest == "n0");
+                assert!(packet.src == "n0" && packet.dest == "c1");
+                assert_eq!(packet.body.in_reply_to, Some(7));
+                assert!(matches!(packet.body.payload, Payload::GenerateOk { .. }));
+            }
+            packet => panic!("Expected a Forward, got {:?}", packet),
+        }
+        assert!(packets[2].src == "n1" && packets[2].dest == "c2");
+        assert_eq!(packets[2].body.payload, Payload::WriteOk);
+    }
+
+    #[test]
+    fn test_paxos_lin_kv() {
+        let nodes = ["n0", "n1", "n2"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let mut handlers: Vec<PaxosLinKvHandler> = nodes
+            .iter()
+            .map(|_| {
+                PaxosLinKvHandler::new(
+                    MultiPaxos::new(Duration::from_secs(60), Duration::ZERO),
+                    KvStore::default(),
+                )
+            })
+            .collect();
+        let tick = |handlers: &mut Vec<PaxosLinKvHandler>, node: usize, isolated| {
+            let responses: Vec<MessageResponse> = handlers[node].handle_tick(&states[node]).into();
+            let queue = responses.into_iter().map(|r| (node, r)).collect();
+            deliver_messages(handlers, &states, queue, isolated)
+        };
+        let cas = |from: i64, to: i64| Payload::Cas {
+            key: 1.into(),
+            from: from.into(),
+            to: to.into(),
+            create_if_not_exists: true,
+        };
+
+        assert!(matches!(
+            send_request(&mut handlers, &states, 1, 1, cas(0, 5)),
+            Payload::Error { code: 11, .. }
+        ));
+        // NOTE: The ballot of n0 is promised by all Nodes and n0 leads without another phase 1.
+        handlers[0].consensus.leader_deadline = Instant::now();
+        tick(&mut handlers, 0, None);
+        assert!(handlers[0].consensus.is_leader());
+        assert_eq!(
+            handlers[0].consensus.ballot,
+            Ballot {
+                round: 1,
+                node_number: 0
+            }
+        );
+        assert!(handlers
+            .iter()
+            .all(|h| h.consensus.leader == Some(states[0].node_id)));
+
+        assert_eq!(
+            send_request(&mut handlers, &states, 1, 2, cas(0, 5)),
+            Payload::CasOk
+        );
+        assert_eq!(
+            send_request(&mut handlers, &states, 2, 3, cas(5, 6)),
+            Payload::CasOk
+        );
+        assert_eq!(
+            send_request(&mut handlers, &states, 0, 3, cas(5, 6)),
+            Payload::CasOk
+        );
+        tick(&mut handlers, 0, None);
+        for handler in handlers.iter() {
+            assert_eq!(handler.state_machine.store.get("1"), Some(&6.into()));
+            assert_eq!(handler.consensus.commit_index, 2);
+        }
+
+        // NOTE: The value n0 proposes while it is partitioned is never chosen, n1 takes over
+        // with a higher ballot and n0 learns the value chosen by n1 for the slot instead.
+        let responses: Vec<MessageResponse> = handlers[0]
+            .handle_message(
+                &Packet {
+                    src: NodeId::from("c1"),
+                    dest: states[0].node_id,
+                    body: Message {
+                        msg_id: Some(4),
+                        in_reply_to: None,
+                        payload: cas(6, 7),
+                    },
+                },
+                &states[0],
+            )
+            .into();
+        let queue = responses.into_iter().map(|r| (0, r)).collect();
+        assert!(deliver_messages(&mut handlers, &states, queue, Some(0)).is_empty());
+        handlers[1].consensus.leader_deadline = Instant::now();
+        tick(&mut handlers, 1, Some(0));
+        assert!(handlers[1].consensus.is_leader());
+        assert_eq!(
+            send_request(&mut handlers, &states, 2, 5, cas(6, 8)),
+            Payload::CasOk
+        );
+        assert!(tick(&mut handlers, 1, None).is_empty());
+        assert!(!handlers[0].consensus.is_leader());
+        for handler in handlers.iter() {
+            assert_eq!(handler.state_machine.store.get("1"), Some(&8.into()));
+            assert_eq!(handler.consensus.chosen, handlers[1].consensus.chosen);
+        }
+    }
+
+    #[test]
+    fn test_txn_list_append() {
+        let nodes = ["n0", "n1", "n2"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let now = Rc::new(Cell::new(100));
+        let mut handlers: Vec<TxnListAppendHandler> = nodes
+            .iter()
+            .map(|_| {
+                let clock = HybridClock::with_clock(Box::new(TestClock(now.clone())));
+                TxnListAppendHandler::new(clock, Duration::ZERO)
+            })
+            .collect();
+
+        let txn = |handlers: &mut Vec<TxnListAppendHandler>, node: usize, txn: &str| {
+            let packet: Packet = serde_json::from_str(&format!(
+                r#"{{"src":"c1","dest":"n{}","body":{{"type":"txn","msg_id":1,"txn":{}}}}}"#,
+                node, txn
+            ))
+            .unwrap();
+            let responses: Vec<MessageResponse> =
+                handlers[node].handle_message(&packet, &states[node]).into();
+            responses.into_iter().map(|r| (node, r)).collect()
+        };
+        let reply = |delivery: &Delivery| -> String {
+            match delivery.replies.as_slice() {
+                [(_, _, payload)] => serde_json::to_string(payload).unwrap(),
+                replies => panic!("Expected a single reply, got {:?}", replies),
+            }
+        };
+        let never = |_: usize, _: &NodeId, _: &Payload| false;
+        let committed_to_n2 = |_: usize, dest: &NodeId, payload: &Payload| {
+            *dest == "n2" && matches!(payload, Payload::TxnCommitted { .. })
+        };
 
-    fn cmp_node_ids(id1: &str, id2: &str) -> Ordering {
-        (id1[1..].parse::<usize>().unwrap_or(0)).cmp(&id2[1..].parse::<usize>().unwrap_or(0))
+        // NOTE: n1 only replies once the certified commit has been installed. The commit
+        // arrives late at n2.
+        let queue = txn(
+            &mut handlers,
+            1,
+            r#"[["r",1,null],["append",1,3],["r",1,null]]"#,
+        );
+        let late = deliver_filtered(
+            &mut handlers,
+            &states,
+            queue,
+            &committed_to_n2,
+            &mut no_service,
+        );
+        assert_eq!(
+            reply(&late),
+            r#"{"type":"txn_ok","txn":[["r",1,[]],["append",1,3],["r",1,[3]]]}"#
+        );
+        assert_eq!(late.held.len(), 1);
+        assert!(handlers[1].pending.is_empty());
+
+        // NOTE: The certifier detects the conflict with the commit n2 has not received yet.
+        let queue = txn(&mut handlers, 2, r#"[["r",1,null],["append",1,4]]"#);
+        let delivery = deliver_filtered(&mut handlers, &states, queue, &never, &mut no_service);
+        assert!(reply(&delivery).contains(r#""code":30"#));
+        let queue = txn(&mut handlers, 2, r#"[["r",1,null]]"#);
+        let delivery = deliver_filtered(&mut handlers, &states, queue, &never, &mut no_service);
+        assert_eq!(reply(&delivery), r#"{"type":"txn_ok","txn":[["r",1,[]]]}"#);
+
+        // NOTE: The late commit is ordered after the snapshot n2 has read from, so it does not
+        // change it.
+        let snapshot = handlers[2]
+            .log
+            .last()
+            .map(|c| c.commit_ts)
+            .unwrap_or_default();
+        deliver_filtered(&mut handlers, &states, late.held, &never, &mut no_service);
+        assert_eq!(handlers[2].store.read(1, snapshot), Vec::<i64>::new());
+        let queue = txn(&mut handlers, 2, r#"[["append",2,5],["r",1,null]]"#);
+        let delivery = deliver_filtered(&mut handlers, &states, queue, &never, &mut no_service);
+        assert_eq!(
+            reply(&delivery),
+            r#"{"type":"txn_ok","txn":[["append",2,5],["r",1,[3]]]}"#
+        );
+
+        // NOTE: The last commit is lost on its way to n1, which asks the certifier for it.
+        let lost = |_: usize, dest: &NodeId, payload: &Payload| {
+            *dest == "n1" && matches!(payload, Payload::TxnCommitted { .. })
+        };
+        let queue = txn(&mut handlers, 0, r#"[["append",2,6]]"#);
+        let delivery = deliver_filtered(&mut handlers, &states, queue, &lost, &mut no_service);
+        assert_eq!(delivery.held.len(), 1);
+        assert_eq!(handlers[1].log.len(), 2);
+        assert!(matches!(
+            handlers[0].handle_tick(&states[0]),
+            Collection::None
+        ));
+        let sync: Vec<MessageResponse> = handlers[1].handle_tick(&states[1]).into();
+        let queue = sync.into_iter().map(|r| (1, r)).collect();
+        deliver_filtered(&mut handlers, &states, queue, &never, &mut no_service);
+        for handler in handlers.iter() {
+            assert_eq!(handler.log, handlers[0].log);
+            assert_eq!(handler.store, handlers[0].store);
+        }
+        assert_eq!(
+            handlers[1].store.read(2, handlers[1].clock.last),
+            vec![5, 6]
+        );
+
+        // NOTE: Concurrent transactions do not see each other and the second one to commit an
+        // append to the same key aborts.
+        let mut store = MvccStore::default();
+        let mut clock = HybridClock::with_clock(Box::new(TestClock(now.clone())));
+        let mut t1 = Transaction::new(clock.now(0));
+        let mut t2 = Transaction::new(clock.now(0));
+        t1.execute(&store, &[MicroOp::Append { key: 2, element: 1 }]);
+        assert!(store.commit(&t1, clock.now(0)).is_ok());
+        let txn = t2.execute(
+            &store,
+            &[
+                MicroOp::Append { key: 2, element: 2 },
+                MicroOp::Read {
+                    key: 2,
+                    value: None,
+                },
+            ],
+        );
+        assert_eq!(
+            txn[1],
+            MicroOp::Read {
+                key: 2,
+                value: Some(vec![2])
+            }
+        );
+        assert_eq!(store.commit(&t2, clock.now(0)), Err(2));
+        assert_eq!(store.read(2, clock.now(0)), vec![1]);
+    }
+
+    #[derive(Default)]
+    struct TestResource {
+        prepared: HashMap<TxnId, i64>,
+        committed: Vec<i64>,
+    }
+
+    impl Resource for TestResource {
+        fn prepare(&mut self, txn_id: &TxnId, operations: &serde_json::Value) -> bool {
+            match operations.as_i64() {
+                Some(value) if value >= 0 => {
+                    self.prepared.insert(*txn_id, value);
+                    true
+                }
+                _ => false,
+            }
+        }
+        fn commit(&mut self, txn_id: &TxnId) {
+            self.committed.extend(self.prepared.remove(txn_id));
+        }
+        fn abort(&mut self, txn_id: &TxnId) {
+            self.prepared.remove(txn_id);
+        }
     }
 
     #[test]
-    fn test_is_lower_node_id() {
-        assert!(is_lower_node_id("n1", "n2"));
-        assert!(!is_lower_node_id("n1", "n1"));
-        assert!(!is_lower_node_id("n2", "n1"));
+    fn test_two_phase_commit() {
+        let nodes = ["n0", "n1", "n2"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let mut coordinator = Coordinator::new(Duration::from_secs(60));
+        let mut participants: Vec<Participant<TestResource>> = (0..2)
+            .map(|_| Participant::new(TestResource::default(), Duration::from_secs(60)))
+            .collect();
+
+        // NOTE: Delivers the Messages of the coordinator (n0) and the participants (n1 and n2)
+        // until there are none left, except the ones for which drop returns true.
+        let run = |coordinator: &mut Coordinator,
+                   participants: &mut Vec<Participant<TestResource>>,
+                   queue: Vec<(usize, MessageResponse)>,
+                   drop: &dyn Fn(&NodeId, &Payload) -> bool| {
+            let mut handlers: Vec<&mut dyn MessageHandler> = vec![coordinator];
+            handlers.extend(
+                participants
+                    .iter_mut()
+                    .map(|p| p as &mut dyn MessageHandler),
+            );
+            let hold = |_: usize, dest: &NodeId, payload: &Payload| drop(dest, payload);
+            deliver_filtered(&mut handlers, &states, queue, &hold, &mut no_service);
+        };
+        let operations = |a: i64, b: i64| {
+            HashMap::from([(states[1].node_id, a.into()), (states[2].node_id, b.into())])
+        };
+        let keep = |_: &NodeId, _: &Payload| false;
+
+        let (t1, prepares) = coordinator.begin(operations(1, 2), &states[0]);
+        run(
+            &mut coordinator,
+            &mut participants,
+            prepares.into_iter().map(|r| (0, r)).collect(),
+            &keep,
+        );
+        let (t2, prepares) = coordinator.begin(operations(3, -1), &states[0]);
+        run(
+            &mut coordinator,
+            &mut participants,
+            prepares.into_iter().map(|r| (0, r)).collect(),
+            &keep,
+        );
+        assert_eq!(
+            coordinator.take_decided(),
+            vec![(t1, Decision::Commit), (t2, Decision::Abort)]
+        );
+        assert_eq!(participants[0].resource.committed, vec![1]);
+        assert_eq!(participants[1].resource.committed, vec![2]);
+        assert!(participants
+            .iter()
+            .all(|p| p.resource.prepared.is_empty() && p.prepared.is_empty()));
+
+        // NOTE: n2 does not receive the decision and asks the coordinator for it. A retried
+        // decision is not applied again.
+        let (t3, prepares) = coordinator.begin(operations(4, 5), &states[0]);
+        run(
+            &mut coordinator,
+            &mut participants,
+            prepares.into_iter().map(|r| (0, r)).collect(),
+            &|dest, payload| *dest == "n2" && matches!(payload, Payload::TxnCommit { .. }),
+        );
+        assert_eq!(coordinator.take_decided(), vec![(t3, Decision::Commit)]);
+        assert!(participants[1].prepared.contains_key(&t3));
+        participants[1].query_timeout = Duration::ZERO;
+        let queries: Vec<MessageResponse> = participants[1].handle_tick(&states[2]).into();
+        assert_eq!(queries.len(), 1);
+        run(
+            &mut coordinator,
+            &mut participants,
+            queries.into_iter().map(|r| (2, r)).collect(),
+            &keep,
+        );
+        let retry = MessageResponse::Ack {
+            src: None,
+            dest: states[2].node_id,
+            in_reply_to: None,
+            payload: Payload::TxnCommit { txn_id: t3 },
+        };
+        run(&mut coordinator, &mut participants, vec![(0, retry)], &keep);
+        assert_eq!(participants[1].resource.committed, vec![2, 5]);
+
+        // NOTE: The prepare for n2 is lost, so the transaction is aborted after the timeout.
+        let (t4, prepares) = coordinator.begin(operations(6, 7), &states[0]);
+        run(
+            &mut coordinator,
+            &mut participants,
+            prepares.into_iter().map(|r| (0, r)).collect(),
+            &|dest, payload| *dest == "n2" && matches!(payload, Payload::TxnPrepare { .. }),
+        );
+        assert!(matches!(
+            coordinator.handle_tick(&states[0]),
+            Collection::None
+        ));
+        coordinator.prepare_timeout = Duration::ZERO;
+        let aborts: Vec<MessageResponse> = coordinator.handle_tick(&states[0]).into();
+        run(
+            &mut coordinator,
+            &mut participants,
+            aborts.into_iter().map(|r| (0, r)).collect(),
+            &keep,
+        );
+        assert_eq!(coordinator.take_decided(), vec![(t4, Decision::Abort)]);
+        assert_eq!(participants[0].resource.committed, vec![1, 4]);
+        assert!(participants[0].resource.prepared.is_empty());
+        // NOTE: The late prepare is answered with a vote to abort.
+        let prepare = MessageResponse::Ack {
+            src: None,
+            dest: states[2].node_id,
+            in_reply_to: None,
+            payload: Payload::TxnPrepare {
+                txn_id: t4,
+                operations: 7.into(),
+            },
+        };
+        run(
+            &mut coordinator,
+            &mut participants,
+            vec![(0, prepare)],
+            &keep,
+        );
+        assert!(participants[1].resource.prepared.is_empty());
+        assert_eq!(participants[1].decisions.get(&t4), Some(&Decision::Abort));
+    }
+
+    #[test]
+    fn test_lin_tso_client() {
+        let mut client: LinTsoClient<usize> = LinTsoClient::new(Duration::from_secs(60));
+        let ts_ok = |in_reply_to: usize, ts: u64| Packet {
+            src: NodeId::from("lin-tso"),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: None,
+                in_reply_to: Some(in_reply_to),
+                payload: Payload::TsOk { ts },
+            },
+        };
+        let rpc = |msg_id: usize| MessageResponse::Request {
+            dest: NodeId::from("lin-tso"),
+            msg_id,
+            payload: Payload::Ts,
+        };
+
+        // NOTE: Requests made while an RPC is in flight are batched into the next one.
+        assert_eq!(client.next_timestamp(1), Some(rpc(1)));
+        assert_eq!(client.next_timestamp(2), None);
+        assert_eq!(client.next_timestamp(3), None);
+        assert!(client.handle_reply(&ts_ok(2, 5)).is_none());
+        assert_eq!(
+            client.handle_reply(&ts_ok(1, 10)),
+            Some(TsReply {
+                timestamps: vec![(1, (10, 0))],
+                next: Some(rpc(2))
+            })
+        );
+        assert_eq!(client.next_timestamp(4), None);
+
+        // NOTE: An overdue RPC is sent again and the reply to the old one is ignored.
+        assert_eq!(client.handle_tick(), None);
+        client.timeout = Duration::ZERO;
+        assert_eq!(client.handle_tick(), Some(rpc(3)));
+        client.timeout = Duration::from_secs(60);
+        assert!(client.handle_reply(&ts_ok(2, 11)).is_none());
+        assert_eq!(
+            client.handle_reply(&ts_ok(3, 12)),
+            Some(TsReply {
+                timestamps: vec![(2, (12, 0)), (3, (12, 1)), (4, (12, 2))],
+                next: None
+            })
+        );
+        assert!(client.in_flight.is_none());
+    }
+
+    #[test]
+    fn test_datomic() {
+        let nodes = ["n0", "n1"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let mut handlers: Vec<DatomicHandler> = nodes
+            .iter()
+            .map(|_| {
+                let strategy = IdStrategy::Striding {
+                    seed_from_clock: false,
+                };
+                DatomicHandler::new(strategy, Duration::from_secs(60))
+            })
+            .collect();
+        let mut services = [KvStore::default(), KvStore::default()];
+
+        // NOTE: Answers the requests to "lin-kv" (services[0]) and "lww-kv" (services[1]),
+        // except the ones for which hold returns true, and returns the replies to Clients and
+        // the held requests.
+        let run = |handlers: &mut Vec<DatomicHandler>,
+                   services: &mut [KvStore; 2],
+                   queue: Vec<(usize, MessageResponse)>,
+                   hold: &dyn Fn(&Payload) -> bool| {
+            let hold = |_: usize, _: &NodeId, payload: &Payload| hold(payload);
+            let mut service = |dest: &NodeId, payload: &Payload| {
+                let service = &mut services[usize::from(*dest == "lww-kv")];
+                let reply = serde_json::to_string(&service.apply(payload)).unwrap();
+                serde_json::from_str(&reply).unwrap()
+            };
+            let delivery = deliver_filtered(handlers, &states, queue, &hold, &mut service);
+            let replies: Vec<Payload> = delivery.replies.into_iter().map(|r| r.2).collect();
+            (replies, delivery.held)
+        };
+        let txn = |handlers: &mut Vec<DatomicHandler>, node: usize, txn: &str| {
+            let packet: Packet = serde_json::from_str(&format!(
+                r#"{{"src":"c1","dest":"n{}","body":{{"type":"txn","msg_id":1,"txn":{}}}}}"#,
+                node, txn
+            ))
+            .unwrap();
+            let responses: Vec<MessageResponse> =
+                handlers[node].handle_message(&packet, &states[node]).into();
+            responses.into_iter().map(|r| (node, r)).collect()
+        };
+        let txn_ok = |txn: &str| -> Payload {
+            serde_json::from_str(&format!(r#"{{"type":"txn_ok","txn":{}}}"#, txn)).unwrap()
+        };
+        let never = |_: &Payload| false;
+
+        let queue = txn(&mut handlers, 0, r#"[["append",1,1],["r",1,null]]"#);
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["append",1,1],["r",1,[1]]]"#)]);
+
+        // NOTE: The "cas" of n1 is held back until n0 has advanced the root, so n1 executes
+        // its transaction again from the new root.
+        let queue = txn(
+            &mut handlers,
+            1,
+            r#"[["r",1,null],["append",1,2],["append",2,3]]"#,
+        );
+        let is_cas = |payload: &Payload| matches!(payload, Payload::Cas { .. });
+        let (replies, held) = run(&mut handlers, &mut services, queue, &is_cas);
+        assert!(replies.is_empty() && held.len() == 1);
+        let queue = txn(&mut handlers, 0, r#"[["append",1,4]]"#);
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["append",1,4]]"#)]);
+        let (replies, _) = run(&mut handlers, &mut services, held, &never);
+        assert_eq!(
+            replies,
+            vec![txn_ok(r#"[["r",1,[1,4]],["append",1,2],["append",2,3]]"#)]
+        );
+        assert!(handlers[1].txns.is_empty());
+
+        // NOTE: n0 only reads the thunks it has not cached.
+        let cached = handlers[0].thunks.len();
+        let queue = txn(&mut handlers, 0, r#"[["r",1,null],["r",2,null]]"#);
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["r",1,[1,4,2]],["r",2,[3]]]"#)]);
+        assert_eq!(handlers[0].thunks.len(), cached + 3);
+
+        // NOTE: The "cas" of n1 is applied, but its reply is lost and n0 advances the root
+        // before n1 sends it again. The resent "cas" fails, but n1 finds its element in the
+        // lists of the new root instead of appending it again.
+        let queue = txn(&mut handlers, 1, r#"[["append",1,5]]"#);
+        let (replies, held) = run(&mut handlers, &mut services, queue, &is_cas);
+        assert!(replies.is_empty() && held.len() == 1);
+        let (_, MessageResponse::Request { payload: cas, .. }) = &held[0] else {
+            panic!("Expected a cas, got {:?}", held);
+        };
+        assert_eq!(services[0].apply(cas), Payload::CasOk);
+        let queue = txn(&mut handlers, 0, r#"[["append",1,6]]"#);
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["append",1,6]]"#)]);
+        handlers[1].lin_kv.timeout = Duration::ZERO;
+        let resent: Vec<MessageResponse> = handlers[1].handle_tick(&states[1]).into();
+        let queue = resent.into_iter().map(|r| (1, r)).collect();
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["append",1,5]]"#)]);
+        assert!(handlers[1].txns.is_empty());
+        let queue = txn(&mut handlers, 0, r#"[["r",1,null]]"#);
+        let (replies, _) = run(&mut handlers, &mut services, queue, &never);
+        assert_eq!(replies, vec![txn_ok(r#"[["r",1,[1,4,2,5,6]]]"#)]);
+    }
+
+    #[test]
+    fn test_datomic_packet_handler() {
+        let input = [
+            r#"{"src":"c0","dest":"n0","body":{"type":"init","msg_id":1,"node_id":"n0","node_ids":["n0","n1"]}}"#,
+            r#"{"src":"c1","dest":"n0","body":{"type":"txn","msg_id":1,"txn":[["append",1,1]]}}"#,
+            r#"{"src":"lin-kv","dest":"n0","body":{"type":"error","in_reply_to":1,"code":20,"text":""}}"#,
+        ];
+        let strategy = IdStrategy::Striding {
+            seed_from_clock: false,
+        };
+        let mut handler = DatomicHandler::new(strategy, Duration::from_secs(60));
+        let packets = run_packet_handler(&input, &mut handler, Default::default());
+
+        // NOTE: The list thunk and the map thunk are written in the same step, but the
+        // service does not understand Batches.
+        let writes: Vec<&Packet> = packets.iter().filter(|p| p.dest == "lww-kv").collect();
+        assert_eq!(writes.len(), 2);
+        assert!(writes
+            .iter()
+            .all(|p| matches!(p.body.payload, Payload::Write { .. }) && p.body.msg_id.is_some()));
+    }
+
+    #[test]
+    fn test_sharded_kv() {
+        let nodes = ["n0", "n1", "n2"];
+        let states: Vec<NodeInfo> = nodes
+            .iter()
+            .map(|n| create_node_info(n, &nodes, Default::default()))
+            .collect();
+        let members: Vec<NodeId> = states.iter().map(|s| s.node_id).collect();
+
+        // NOTE: Only the keys of the removed member move to other members.
+        let ring = HashRing::new(members.clone(), 32);
+        let smaller = HashRing::new([members[0], members[1]], 32);
+        let keys: Vec<serde_json::Value> = (0..300).map(|k: i64| k.into()).collect();
+        let owners: Vec<NodeId> = keys.iter().map(|k| ring.owner(k).unwrap()).collect();
+        for member in members.iter() {
+            assert!(owners.iter().filter(|o| *o == member).count() > 50);
+        }
+        for (key, owner) in keys.iter().zip(owners.iter()) {
+            let new_owner = smaller.owner(key).unwrap();
+            assert!(new_owner == *owner || *owner == members[2]);
+            assert!(new_owner != members[2]);
+        }
+
+        let mut handlers: Vec<ShardedKvHandler> = nodes
+            .iter()
+            .map(|_| ShardedKvHandler::new(KvHandler::new(Duration::ZERO), 32))
+            .collect();
+        let key = keys
+            .iter()
+            .find(|k| ring.owner(k) == Some(members[2]))
+            .unwrap()
+            .clone();
+        let write = Packet {
+            src: NodeId::from("c1"),
+            dest: states[0].node_id,
+            body: Message {
+                msg_id: Some(1),
+                in_reply_to: None,
+                payload: Payload::Write {
+                    key: key.clone(),
+                    value: 5.into(),
+                },
+            },
+        };
+        let Collection::One(MessageResponse::NoAck {
+            dest,
+            payload: Payload::Forward { packet },
+            ..
+        }) = handlers[0].handle_message(&write, &states[0])
+        else {
+            panic!("Expected the write to be forwarded to its owner");
+        };
+        assert!(dest == "n2" && packet.src == "c1" && packet.dest == "n2");
+        match handlers[2].handle_message(&packet, &states[2]) {
+            Collection::One(MessageResponse::NoAck {
+                dest,
+                in_reply_to,
+                payload,
+                ..
+            }) => {
+                assert!(dest == "c1");
+                assert_eq!(in_reply_to, Some(1));
+                assert_eq!(payload, Payload::WriteOk);
+            }
+            responses => panic!("Expected a WriteOk, got {:?}", responses),
+        }
+
+        // NOTE: n2 leaves and hands its keys over to their new owners, but keeps them until
+        // the handoffs have been acknowledged.
+        let smaller_members = [members[0], members[1]];
+        let handoffs = handlers[2].set_members(smaller_members, &states[2]);
+        assert_eq!(handoffs.len(), 2);
+        let new_owner = members
+            .iter()
+            .position(|m| Some(*m) == smaller.owner(&key))
+            .unwrap();
+        let to_new_owner = |_: usize, dest: &NodeId, _: &Payload| *dest == members[new_owner];
+        let queue = handoffs.into_iter().map(|r| (2, r)).collect();
+        let handoff = deliver_filtered(
+            &mut handlers,
+            &states,
+            queue,
+            &to_new_owner,
+            &mut no_service,
+        );
+        assert_eq!(handoff.held.len(), 1);
+        assert!(!handlers[2].inner.store.store.is_empty());
+        // NOTE: The other member has not applied the change yet, so it ignores the handoff
+        // until it is sent again.
+        assert_eq!(handlers[2].inner.outgoing.len(), 2);
+        let never = |_: usize, _: &NodeId, _: &Payload| false;
+        for i in 0..2 {
+            let handoffs = handlers[i].set_members(smaller_members, &states[i]);
+            let queue = handoffs.into_iter().map(|r| (i, r)).collect();
+            deliver_filtered(&mut handlers, &states, queue, &never, &mut no_service);
+        }
+        let resent: Vec<MessageResponse> = handlers[2].handle_tick(&states[2]).into();
+        let queue = resent.into_iter().map(|r| (2, r)).collect();
+        deliver_filtered(
+            &mut handlers,
+            &states,
+            queue,
+            &to_new_owner,
+            &mut no_service,
+        );
+        assert_eq!(handlers[2].inner.outgoing.len(), 1);
+
+        // NOTE: The new owner defers requests for the key until the handoff has arrived.
+        let request = |msg_id: usize, payload: Payload| Packet {
+            src: NodeId::from("c1"),
+            dest: members[new_owner],
+            body: Message {
+                msg_id: Some(msg_id),
+                in_reply_to: None,
+                payload,
+            },
+        };
+        let read = request(
+            2,
+            Payload::Read {
+                key: Some(key.clone()),
+            },
+        );
+        assert!(matches!(
+            handlers[new_owner].handle_message(&read, &states[new_owner]),
+            Collection::None
+        ));
+        let delivery = deliver_filtered(
+            &mut handlers,
+            &states,
+            handoff.held.clone(),
+            &never,
+            &mut no_service,
+        );
+        assert_eq!(
+            delivery.replies,
+            vec![(
+                NodeId::from("c1"),
+                Some(2),
+                Payload::ReadOk(ReadValue::Value { value: 5.into() })
+            )]
+        );
+        assert!(handlers[2].inner.store.store.is_empty());
+        assert!(handlers[2].inner.outgoing.is_empty());
+
+        // NOTE: A retried handoff is acknowledged, but does not overwrite later writes.
+        let write = request(
+            3,
+            Payload::Write {
+                key: key.clone(),
+                value: 7.into(),
+            },
+        );
+        handlers[new_owner].handle_message(&write, &states[new_owner]);
+        let delivery = deliver_filtered(
+            &mut handlers,
+            &states,
+            handoff.held,
+            &never,
+            &mut no_service,
+        );
+        assert_eq!(delivery.delivered, 2);
+        assert_eq!(
+            send_request(&mut handlers, &states, new_owner, 4, read.body.payload),
+            Payload::ReadOk(ReadValue::Value { value: 7.into() })
+        );
+
+        assert!(matches!(
+            handlers[2].handle_tick(&states[2]),
+            Collection::None
+        ));
+    }
+
+    #[test]
+    fn test_broadcast_delta() {
+        let topology = HashMap::from([(
+            NodeId::from("n0"),
+            vec![NodeId::from("n1"), NodeId::from("n2")],
+        )]);
+        let state = create_node_info("n0", &["n0", "n1", "n2"], topology);
+        let mut handler = BroadcastHandler::new(BroadcastMode::Delta {
+            interval: Duration::ZERO,
+        });
+        handler.messages.extend(&[1, 2]);
+
+        let packet = |src: &str, payload| Packet {
+            src: NodeId::from(src),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: None,
+                in_reply_to: None,
+                payload,
+            },
+        };
+        let deltas = |handler: &mut BroadcastHandler| -> HashMap<NodeId, Payload> {
+            Vec::from(handler.handle_tick(&state))
+                .into_iter()
+                .map(|r| match r {
+                    MessageResponse::NoAck { dest, payload, .. } => (dest, payload),
+                    _ => panic!("Deltas are sent without Ack"),
+                })
+                .collect()
+        };
+
+        let sent = deltas(&mut handler);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            sent[&NodeId::from("n1")],
+            Payload::BroadcastDelta {
+                messages: vec![1, 2],
+                up_to: 2,
+                received: 0
+            }
+        );
+
+        let ack = packet("n1", Payload::BroadcastDeltaOk { received: 2 });
+        handler.handle_message(&ack, &state);
+        let delta = packet(
+            "n2",
+            Payload::BroadcastDelta {
+                messages: vec![2, 3],
+                up_to: 2,
+                received: 0,
+            },
+        );
+        match handler.handle_message(&delta, &state) {
+            Collection::One(MessageResponse::NoAck { payload, .. }) => {
+                assert_eq!(payload, Payload::BroadcastDeltaOk { received: 2 })
+            }
+            responses => panic!("Expected a BroadcastDeltaOk, got {:?}", responses),
+        }
+
+        let sent = deltas(&mut handler);
+        assert_eq!(
+            sent[&NodeId::from("n1")],
+            Payload::BroadcastDelta {
+                messages: vec![3],
+                up_to: 3,
+                received: 0
+            }
+        );
+        assert_eq!(
+            sent[&NodeId::from("n2")],
+            Payload::BroadcastDelta {
+                messages: vec![1],
+                up_to: 3,
+                received: 2
+            }
+        );
+
+        // NOTE: Nothing is sent once all messages have been acknowledged.
+        for node in ["n1", "n2"] {
+            handler.handle_message(
+                &packet(node, Payload::BroadcastDeltaOk { received: 3 }),
+                &state,
+            );
+        }
+        assert!(deltas(&mut handler).is_empty());
+    }
+
+    #[test]
+    fn test_message_store() {
+        let mut store = MessageStore::new();
+        assert!(store.insert(3));
+        assert!(!store.insert(3));
+        assert_eq!(store.extend(&[1, 3, 2, 1]), vec![1, 2]);
+
+        let snapshot = store.snapshot();
+        assert!(store.insert(4));
+        assert_eq!(snapshot, vec![3, 1, 2]);
+        assert_eq!(store.snapshot(), vec![3, 1, 2, 4]);
+        assert_eq!(store.len(), 4);
+    }
+
+    #[test]
+    fn test_broadcast_forwards_once() {
+        let topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (NodeId::from("n1"), vec![NodeId::from("n0")]),
+        ]);
+        let mut state = create_node_info("n0", &["n0", "n1"], Default::default());
+        let topology = IndexedTopology::new(&state.server_nodes, &topology);
+        state.broadcast_topology = build_broadcast_topology(&state.node_id, &topology);
+        let mut handler = BroadcastHandler::new(BroadcastMode::Forward);
+
+        let broadcast = Packet {
+            src: NodeId::from("c1"),
+            dest: NodeId::from("n0"),
+            body: Message {
+                msg_id: Some(1),
+                in_reply_to: None,
+                payload: Payload::Broadcast { message: 7 },
+            },
+        };
+        assert_eq!(
+            Vec::from(handler.handle_message(&broadcast, &state)).len(),
+            2
+        );
+        // NOTE: A retried Broadcast is only acknowledged.
+        assert_eq!(
+            Vec::from(handler.handle_message(&broadcast, &state)).len(),
+            1
+        );
+
+        let forwarded = Packet {
+            src: NodeId::from("n1"),
+            ..broadcast
+        };
+        assert_eq!(handler.handle_message(&forwarded, &state), Collection::None);
+        assert_eq!(handler.messages.len(), 1);
+    }
+
+    /// Reads the bytes sent through the channel, so a PacketHandler can be fed while it runs.
+    struct ChannelReader {
+        receiver: mpsc::Receiver<Vec<u8>>,
+        buffer: Vec<u8>,
+    }
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.buffer.is_empty() {
+                match self.receiver.recv() {
+                    Ok(bytes) => self.buffer = bytes,
+                    Err(_) => return Ok(0),
+                }
+            }
+            let n = buf.len().min(self.buffer.len());
+            buf[..n].copy_from_slice(&self.buffer[..n]);
+            self.buffer.drain(..n);
+            Ok(n)
+        }
+    }
+
+    struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+    impl Write for ChannelWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let _ = self.0.send(buf.to_vec());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_suspect_timeout_with_idle_nodes() {
+        // NOTE: "n3" is part of the Topology, but crashed before sending anything.
+        let nodes = ["n0", "n1", "n2"];
+        let mut handlers: Vec<BroadcastHandler> = nodes
+            .iter()
+            .map(|_| BroadcastHandler::new(BroadcastMode::Forward))
+            .collect();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        let mut packet_handlers = Vec::new();
+        for handler in handlers.iter_mut() {
+            let (input, receiver) = mpsc::channel();
+            let (sender, output) = mpsc::channel();
+            let reader = ChannelReader {
+                receiver,
+                buffer: Vec::new(),
+            };
+            let mut packet_handler = PacketHandler::new(reader, ChannelWriter(sender));
+            packet_handler.set_tick_interval(Duration::from_millis(5));
+            packet_handler.set_broadcast_redundancy(BroadcastRedundancy {
+                paths: 1,
+                suspect_timeout: Some(Duration::from_millis(150)),
+            });
+            packet_handler.add_handler(handler);
+            packet_handlers.push(packet_handler);
+            inputs.push(input);
+            outputs.push((output, String::new()));
+        }
+        let send = |node: usize, line: String| inputs[node].send(line.into_bytes()).unwrap();
+        for (i, node) in nodes.iter().enumerate() {
+            send(
+                i,
+                format!(
+                    r#"{{"src":"c0","dest":"{node}","body":{{"type":"init","msg_id":1,"node_id":"{node}","node_ids":["n0","n1","n2","n3"]}}}}"#
+                ),
+            );
+            send(
+                i,
+                format!(
+                    r#"{{"src":"c0","dest":"{node}","body":{{"type":"topology","msg_id":2,"topology":{{"n0":["n1"],"n1":["n0","n2","n3"],"n2":["n1"],"n3":["n1"]}}}}}}"#
+                ),
+            );
+        }
+
+        // NOTE: Steps all Nodes and delivers the Packets between them for the duration.
+        let mut run = |packet_handlers: &mut Vec<PacketHandler<ChannelWriter>>, duration| {
+            let end = Instant::now() + duration;
+            while Instant::now() < end {
+                for packet_handler in packet_handlers.iter_mut() {
+                    packet_handler.step();
+                }
+                for (output, pending) in outputs.iter_mut() {
+                    while let Ok(bytes) = output.try_recv() {
+                        pending.push_str(&String::from_utf8(bytes).unwrap());
+                    }
+                    while let Some(end) = pending.find('\n') {
+                        let line: String = pending.drain(..=end).collect();
+                        let packet: Packet = serde_json::from_str(&line).unwrap();
+                        if let Some(i) = nodes.iter().position(|n| packet.dest == *n) {
+                            send(i, line);
+                        }
+                    }
+                }
+            }
+        };
+
+        // NOTE: Nothing but Heartbeats is sent for longer than the suspect timeout.
+        run(&mut packet_handlers, Duration::from_millis(400));
+        for packet_handler in packet_handlers.iter() {
+            let suspected = &packet_handler.get_state().suspected_nodes;
+            assert_eq!(*suspected, HashSet::from([NodeId::from("n3")]));
+        }
+
+        send(
+            0,
+            r#"{"src":"c1","dest":"n0","body":{"type":"broadcast","msg_id":1,"message":7}}"#
+                .to_string(),
+        );
+        run(&mut packet_handlers, Duration::from_millis(50));
+        drop(packet_handlers);
+        assert!(handlers.iter().all(|h| h.messages.contains(&7)));
+    }
+
+    fn count_edges(topology: &Topology) -> usize {
+        for (node, neighbours) in topology.iter() {
+            for neighbour in neighbours {
+                assert!(topology[neighbour].contains(node));
+            }
+        }
+        topology.values().map(|n| n.len()).sum::<usize>() / 2
+    }
+
+    #[test]
+    fn test_build_topology() {
+        let nodes: Vec<NodeId> = ["n3", "n10", "n0", "n2", "n1"]
+            .iter()
+            .map(|n| NodeId::from(*n))
+            .collect();
+        let line: Topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (
+                NodeId::from("n1"),
+                vec![NodeId::from("n0"), NodeId::from("n2")],
+            ),
+            (
+                NodeId::from("n2"),
+                vec![NodeId::from("n1"), NodeId::from("n3")],
+            ),
+            (
+                NodeId::from("n3"),
+                vec![NodeId::from("n2"), NodeId::from("n10")],
+            ),
+            (NodeId::from("n10"), vec![NodeId::from("n3")]),
+        ]);
+
+        let given = build_topology(&TopologyStrategy::AsGiven, &nodes, &line);
+        assert_eq!(given, line);
+
+        let tree = build_topology(&TopologyStrategy::SpanningTree, &nodes, &line);
+        assert_eq!(count_edges(&tree), 4);
+
+        let star = build_topology(&TopologyStrategy::Star, &nodes, &line);
+        assert_eq!(count_edges(&star), 4);
+        assert_eq!(star[&NodeId::from("n0")].len(), 4);
+
+        let tree = build_topology(&TopologyStrategy::KAryTree(2), &nodes, &line);
+        assert_eq!(count_edges(&tree), 4);
+        assert_eq!(tree[&NodeId::from("n0")], vec!["n1", "n2"]);
+        assert_eq!(tree[&NodeId::from("n1")], vec!["n0", "n3", "n10"]);
+
+        let full = build_topology(&TopologyStrategy::FullyConnected, &nodes, &line);
+        assert_eq!(count_edges(&full), 10);
+
+        // NOTE: The Topology must not depend on the order of the Node Ids.
+        let mut reversed = nodes.clone();
+        reversed.reverse();
+        for strategy in [
+            TopologyStrategy::SpanningTree,
+            TopologyStrategy::Star,
+            TopologyStrategy::KAryTree(3),
+        ] {
+            assert_eq!(
+                build_topology(&strategy, &nodes, &line),
+                build_topology(&strategy, &reversed, &line)
+            );
+        }
+    }
+
+    #[test]
+    fn test_repair_disconnected_topology() {
+        let nodes: Vec<NodeId> = (0..5).map(|i| NodeId::from(format!("n{}", i))).collect();
+        let mut topology: Topology = HashMap::from([
+            (NodeId::from("n0"), vec![NodeId::from("n3")]),
+            (NodeId::from("n3"), vec![NodeId::from("n0")]),
+            (NodeId::from("n1"), vec![NodeId::from("n4")]),
+            (NodeId::from("n4"), vec![NodeId::from("n1")]),
+        ]);
+
+        let components = connected_components(&nodes, &topology);
+        assert_eq!(
+            components,
+            vec![
+                vec![NodeId::from("n0"), NodeId::from("n3")],
+                vec![NodeId::from("n1"), NodeId::from("n4")],
+                vec![NodeId::from("n2")],
+            ]
+        );
+
+        let bridges = repair_topology(&mut topology, &components);
+        assert_eq!(
+            bridges,
+            vec![
+                (NodeId::from("n0"), NodeId::from("n1")),
+                (NodeId::from("n1"), NodeId::from("n2")),
+            ]
+        );
+        assert_eq!(connected_components(&nodes, &topology).len(), 1);
+        assert!(test_build_broadcast_topology_internal(topology));
+    }
+
+    /// Simulates a Broadcast from origin where every Node forwards the Broadcast once.
+    /// Returns the Nodes that received the Broadcast.
+    fn simulate_broadcast(
+        topologies: &HashMap<NodeId, BroadcastTopology>,
+        origin: &NodeId,
+        failed_link: Option<(&NodeId, &NodeId)>,
+    ) -> HashSet<NodeId> {
+        let mut received = HashSet::from([*origin]);
+        let mut queue = vec![*origin];
+
+        while let Some(node) = queue.pop() {
+            for next in topologies[&node][origin].iter() {
+                let failed = failed_link
+                    .is_some_and(|(n1, n2)| (n1, n2) == (&node, next) || (n2, n1) == (&node, next));
+                if !failed && received.insert(*next) {
+                    queue.push(*next);
+                }
+            }
+        }
+
+        received
+    }
+
+    #[test]
+    fn test_build_redundant_broadcast_topology() {
+        // NOTE: 3x3 Grid
+        let nodes: Vec<NodeId> = (0..9).map(|i| NodeId::from(format!("n{}", i))).collect();
+        let topology: Topology = (0..9)
+            .map(|i: usize| {
+                let (row, col) = (i / 3, i % 3);
+                let mut neighbours = Vec::new();
+                if col > 0 {
+                    neighbours.push(NodeId::from(format!("n{}", i - 1)));
+                }
+                if col < 2 {
+                    neighbours.push(NodeId::from(format!("n{}", i + 1)));
+                }
+                if row > 0 {
+                    neighbours.push(NodeId::from(format!("n{}", i - 3)));
+                }
+                if row < 2 {
+                    neighbours.push(NodeId::from(format!("n{}", i + 3)));
+                }
+                (NodeId::from(format!("n{}", i)), neighbours)
+            })
+            .collect();
+
+        let links: Vec<(&NodeId, &NodeId)> = topology
+            .iter()
+            .flat_map(|(n, neighbours)| neighbours.iter().map(move |m| (n, m)))
+            .collect();
+
+        let indexed = IndexedTopology::new(&nodes, &topology);
+        for paths in [1, 2] {
+            let topologies: HashMap<NodeId, BroadcastTopology> = nodes
+                .iter()
+                .map(|n| (*n, build_redundant_broadcast_topology(n, &indexed, paths)))
+                .collect();
+
+            for origin in nodes.iter() {
+                assert_eq!(
+                    simulate_broadcast(&topologies, origin, None).len(),
+                    nodes.len()
+                );
+                let survives_failures = links.iter().all(|link| {
+                    simulate_broadcast(&topologies, origin, Some(*link)).len() == nodes.len()
+                });
+                assert_eq!(survives_failures, paths == 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_topology_analysis() {
+        let topology: Topology = HashMap::from([
+            (
+                NodeId::from("n0"),
+                vec![NodeId::from("n1"), NodeId::from("n2")],
+            ),
+            (
+                NodeId::from("n1"),
+                vec![NodeId::from("n0"), NodeId::from("n2")],
+            ),
+            (
+                NodeId::from("n2"),
+                vec![NodeId::from("n0"), NodeId::from("n1")],
+            ),
+        ]);
+
+        let broadcast_topologies = topology_analysis::build_broadcast_topologies(&topology, 1);
+        let stats = topology_analysis::analyze_topology(&topology, &broadcast_topologies);
+        assert_eq!(stats.node_count, 3);
+        assert_eq!(stats.edge_count, 3);
+        assert_eq!(stats.diameter, Some(1));
+        assert_eq!(stats.degrees[&NodeId::from("n1")], 2);
+        assert_eq!(stats.expected_hops, 1.0);
+        assert_eq!(stats.redundant_deliveries, 0);
+        assert!(stats.broadcasts.values().all(|b| b.missing.is_empty()));
+
+        let dot = topology_analysis::topology_to_dot(&topology);
+        assert!(dot.contains("\"n0\" -- \"n1\";"));
+        assert_eq!(dot.matches(" -- ").count(), 3);
+
+        let json = topology_analysis::topology_to_json(&topology, &broadcast_topologies).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["stats"]["diameter"], 1);
+        let nodes: Vec<&String> = json["topology"].as_object().unwrap().keys().collect();
+        assert_eq!(nodes, ["n0", "n1", "n2"]);
     }
 
     #[test]
@@ -47,22 +2646,22 @@ mod test {
     }
     #[test]
     fn test_build_broadcast_topology2() {
-        let topology: Topology = HashMap::from([(String::from("n0"), vec![])]);
+        let topology: Topology = HashMap::from([(NodeId::from("n0"), vec![])]);
 
         assert!(test_build_broadcast_topology_internal(topology));
     }
     #[test]
     fn test_build_broadcast_topology3() {
         let topology: Topology =
-            HashMap::from([(String::from("n0"), vec![]), (String::from("n1"), vec![])]);
+            HashMap::from([(NodeId::from("n0"), vec![]), (NodeId::from("n1"), vec![])]);
 
         assert!(!test_build_broadcast_topology_internal(topology));
     }
     #[test]
     fn test_build_broadcast_topology4() {
         let topology: Topology = HashMap::from([
-            (String::from("n0"), vec![String::from("n1")]),
-            (String::from("n1"), vec![String::from("n0")]),
+            (NodeId::from("n0"), vec![NodeId::from("n1")]),
+            (NodeId::from("n1"), vec![NodeId::from("n0")]),
         ]);
 
         assert!(test_build_broadcast_topology_internal(topology));
@@ -71,20 +2670,20 @@ mod test {
     fn test_build_broadcast_topology5() {
         let topology: Topology = HashMap::from([
             (
-                String::from("n0"),
-                vec![String::from("n1"), String::from("n2")],
+                NodeId::from("n0"),
+                vec![NodeId::from("n1"), NodeId::from("n2")],
             ),
             (
-                String::from("n1"),
-                vec![String::from("n3"), String::from("n0")],
+                NodeId::from("n1"),
+                vec![NodeId::from("n3"), NodeId::from("n0")],
             ),
             (
-                String::from("n2"),
-                vec![String::from("n3"), String::from("n0")],
+                NodeId::from("n2"),
+                vec![NodeId::from("n3"), NodeId::from("n0")],
             ),
             (
-                String::from("n3"),
-                vec![String::from("n1"), String::from("n2")],
+                NodeId::from("n3"),
+                vec![NodeId::from("n1"), NodeId::from("n2")],
             ),
         ]);
 
@@ -94,36 +2693,35 @@ mod test {
     fn test_build_broadcast_topology6() {
         let topology: Topology = HashMap::from([
             (
-                String::from("n0"),
-                vec![String::from("n1"), String::from("n3")],
+                NodeId::from("n0"),
+                vec![NodeId::from("n1"), NodeId::from("n3")],
             ),
             (
-                String::from("n1"),
-                vec![String::from("n0"), String::from("n2"), String::from("n4")],
+                NodeId::from("n1"),
+                vec![NodeId::from("n0"), NodeId::from("n2"), NodeId::from("n4")],
             ),
             (
-                String::from("n2"),
-                vec![String::from("n1"), String::from("n5")],
+                NodeId::from("n2"),
+                vec![NodeId::from("n1"), NodeId::from("n5")],
             ),
             (
-                String::from("n3"),
-                vec![String::from("n0"), String::from("n4")],
+                NodeId::from("n3"),
+                vec![NodeId::from("n0"), NodeId::from("n4")],
             ),
             (
-                String::from("n4"),
-                vec![String::from("n1"), String::from("n3"), String::from("n5")],
+                NodeId::from("n4"),
+                vec![NodeId::from("n1"), NodeId::from("n3"), NodeId::from("n5")],
             ),
             (
-                String::from("n5"),
-                vec![String::from("n2"), String::from("n4")],
+                NodeId::from("n5"),
+                vec![NodeId::from("n2"), NodeId::from("n4")],
             ),
         ]);
 
         assert!(test_build_broadcast_topology_internal(topology));
-        assert!(false);
     }
 
-    fn test_build_broadcast_topology_internal(mut topology: Topology) -> bool {
+    fn test_build_broadcast_topology_internal(topology: Topology) -> bool {
         let all_topologies = build_broadcast_topology_internal(topology);
         println!("All Topologies: {:#?}", all_topologies);
 
@@ -132,29 +2730,40 @@ mod test {
 
         check_topology_is_complete(complete_topology)
     }
-    fn check_topology_is_complete(mut topology: BroadcastTopology) -> bool {
+    /// Checks that every Broadcast is received by every other Node exactly once.
+    fn check_topology_is_complete(topology: BroadcastTopology) -> bool {
         let mut valid = true;
-        let mut nodes: HashSet<String> = topology.keys().cloned().collect();
+        let nodes: HashSet<NodeId> = topology.keys().cloned().collect();
 
         for node in nodes.iter() {
-            // TODO: Check if any nodes are in the topology multiple times => Redundant Broadcast
-            let adj = topology.get_mut(node).unwrap().iter().cloned().collect();
+            let mut received: HashMap<&NodeId, usize> = HashMap::new();
+            for receiver in topology[node].iter() {
+                *received.entry(receiver).or_default() += 1;
+            }
 
-            let mut diff = nodes.difference(&adj).collect::<Vec<&String>>();
-            let pos = diff.iter().position(|&i| i == node);
-            if !pos.is_some() {
+            if received.contains_key(node) {
                 println!("Self referencial Broadcast for Node {:?}", node);
-                valid &= false;
-            } else {
-                diff.remove(pos.unwrap());
+                valid = false;
             }
-            if !diff.is_empty() {
+            let redundant: Vec<&NodeId> = received
+                .iter()
+                .filter(|(_, count)| **count > 1)
+                .map(|(receiver, _)| *receiver)
+                .collect();
+            if !redundant.is_empty() {
                 println!(
-                    "Nodes {:?} are missing for {:?}",
-                    diff.into_iter().collect::<Vec<&String>>(),
-                    node
+                    "Nodes {:?} receive Broadcasts from {:?} multiple times",
+                    redundant, node
                 );
-                valid &= false;
+                valid = false;
+            }
+            let missing: Vec<&NodeId> = nodes
+                .iter()
+                .filter(|n| *n != node && !received.contains_key(n))
+                .collect();
+            if !missing.is_empty() {
+                println!("Nodes {:?} are missing for {:?}", missing, node);
+                valid = false;
             }
         }
 
@@ -162,20 +2771,22 @@ mod test {
     }
     fn build_broadcast_topology_internal(
         base_topology: Topology,
-    ) -> HashMap<String, BroadcastTopology> {
-        let mut nodes: Vec<String> = base_topology.keys().cloned().collect();
+    ) -> HashMap<NodeId, BroadcastTopology> {
+        let nodes: Vec<NodeId> = base_topology.keys().cloned().collect();
 
+        let indexed = IndexedTopology::new(&nodes, &base_topology);
         let mut complete_topology = HashMap::new();
         for node in nodes.iter() {
-            let mut broadcast_topology = build_broadcast_topology(node, &nodes, &base_topology);
+            let broadcast_topology = build_broadcast_topology(node, &indexed);
 
-            complete_topology.insert(node.clone(), broadcast_topology);
+            complete_topology.insert(*node, broadcast_topology);
         }
 
         complete_topology
     }
-    fn collect_topologies(topologies: HashMap<String, BroadcastTopology>) -> Topology {
-        let mut nodes: Vec<String> = topologies.keys().cloned().collect();
+    /// Collects the Nodes every Broadcast is sent to, indexed by the origin of the Broadcast.
+    fn collect_topologies(topologies: HashMap<NodeId, BroadcastTopology>) -> Topology {
+        let nodes: Vec<NodeId> = topologies.keys().cloned().collect();
         let mut complete_topology: Topology = HashMap::with_capacity(nodes.len());
 
         for node in nodes.iter() {
@@ -188,10 +2799,179 @@ mod test {
             }
         }
 
-        for node in nodes.iter() {
-            let mut vec = complete_topology.get_mut(node).unwrap();
+        complete_topology
+    }
+
+    /// Builds an undirected Topology from a list of links between Node indices.
+    fn topology_from_edges(num_nodes: usize, edges: &[(usize, usize)]) -> Topology {
+        let mut topology: Topology = (0..num_nodes)
+            .map(|i| (NodeId::from(format!("n{}", i)), vec![]))
+            .collect();
+        for &(n1, n2) in edges {
+            let (n1, n2) = (
+                NodeId::from(format!("n{}", n1)),
+                NodeId::from(format!("n{}", n2)),
+            );
+            if n1 == n2 || topology[&n1].contains(&n2) {
+                continue;
+            }
+            topology.get_mut(&n1).unwrap().push(n2);
+            topology.get_mut(&n2).unwrap().push(n1);
         }
+        topology
+    }
 
-        complete_topology
+    /// Grids of up to 6x6 Nodes.
+    fn grid_topology() -> impl Strategy<Value = Topology> {
+        (1..=6usize, 1..=6usize).prop_map(|(rows, cols)| {
+            let mut edges = Vec::new();
+            for row in 0..rows {
+                for col in 0..cols {
+                    let i = row * cols + col;
+                    if col + 1 < cols {
+                        edges.push((i, i + 1));
+                    }
+                    if row + 1 < rows {
+                        edges.push((i, i + cols));
+                    }
+                }
+            }
+            topology_from_edges(rows * cols, &edges)
+        })
+    }
+
+    /// Trees where Node i + 1 is attached to one of the Nodes 0..=i.
+    /// Shrinks towards fewer Nodes and a Star around n0.
+    fn tree_topology() -> impl Strategy<Value = Topology> {
+        prop::collection::vec(any::<prop::sample::Index>(), 0..24).prop_map(|parents| {
+            let edges: Vec<(usize, usize)> = parents
+                .iter()
+                .enumerate()
+                .map(|(i, parent)| (i + 1, parent.index(i + 1)))
+                .collect();
+            topology_from_edges(parents.len() + 1, &edges)
+        })
+    }
+
+    /// Connected random regular Graphs: a ring where every Node is linked to its degree / 2
+    /// closest Nodes on either side (and the opposite Node for odd degrees) with the Node Ids
+    /// shuffled. Shrinks towards fewer Nodes, lower degrees and the unshuffled ring.
+    fn regular_topology() -> impl Strategy<Value = Topology> {
+        (3..=16usize)
+            .prop_flat_map(|num_nodes| (Just(num_nodes), 2..=(num_nodes - 1).min(6)))
+            .prop_filter("odd degrees need an even number of Nodes", |(n, d)| {
+                d % 2 == 0 || n % 2 == 0
+            })
+            .prop_flat_map(|(num_nodes, degree)| {
+                let labels = Just((0..num_nodes).collect::<Vec<usize>>()).prop_shuffle();
+                (Just(num_nodes), Just(degree), labels)
+            })
+            .prop_map(|(num_nodes, degree, labels)| {
+                let mut edges = Vec::new();
+                for i in 0..num_nodes {
+                    for offset in 1..=degree / 2 {
+                        edges.push((labels[i], labels[(i + offset) % num_nodes]));
+                    }
+                    if degree % 2 == 1 {
+                        edges.push((labels[i], labels[(i + num_nodes / 2) % num_nodes]));
+                    }
+                }
+                topology_from_edges(num_nodes, &edges)
+            })
+    }
+
+    /// Random connected Graphs: a random tree with additional random links.
+    fn connected_topology() -> impl Strategy<Value = Topology> {
+        (
+            prop::collection::vec(any::<prop::sample::Index>(), 0..16),
+            prop::collection::vec(
+                (any::<prop::sample::Index>(), any::<prop::sample::Index>()),
+                0..24,
+            ),
+        )
+            .prop_map(|(parents, links)| {
+                let num_nodes = parents.len() + 1;
+                let edges: Vec<(usize, usize)> = parents
+                    .iter()
+                    .enumerate()
+                    .map(|(i, parent)| (i + 1, parent.index(i + 1)))
+                    .chain(
+                        links
+                            .iter()
+                            .map(|(n1, n2)| (n1.index(num_nodes), n2.index(num_nodes))),
+                    )
+                    .collect();
+                topology_from_edges(num_nodes, &edges)
+            })
+    }
+
+    /// Checks that the Broadcast Topologies deliver every Broadcast to every Node exactly once
+    /// when every Node forwards a Broadcast when it receives it.
+    fn check_exactly_once_delivery(topology: &Topology) -> Result<(), TestCaseError> {
+        let mut nodes: Vec<NodeId> = topology.keys().cloned().collect();
+        nodes.sort();
+        let indexed = IndexedTopology::new(&nodes, topology);
+        let topologies: HashMap<NodeId, BroadcastTopology> = nodes
+            .iter()
+            .map(|node| (*node, build_broadcast_topology(node, &indexed)))
+            .collect();
+
+        for origin in nodes.iter() {
+            let mut received: HashMap<&NodeId, usize> = HashMap::from([(origin, 1)]);
+            let mut queue = vec![origin];
+            while let Some(node) = queue.pop() {
+                for next in topologies[node][origin].iter() {
+                    prop_assert!(
+                        topology[node].contains(next),
+                        "{} forwards Broadcasts from {} to {}, which is not a neighbour",
+                        node,
+                        origin,
+                        next
+                    );
+                    let count = received.entry(next).or_default();
+                    *count += 1;
+                    prop_assert_eq!(
+                        *count,
+                        1,
+                        "{} receives the Broadcast from {} multiple times",
+                        next,
+                        origin
+                    );
+                    queue.push(next);
+                }
+            }
+            for node in nodes.iter() {
+                prop_assert!(
+                    received.contains_key(node),
+                    "{} never receives the Broadcast from {}",
+                    node,
+                    origin
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_broadcast_topology_grid(topology in grid_topology()) {
+            check_exactly_once_delivery(&topology)?;
+        }
+
+        #[test]
+        fn test_broadcast_topology_tree(topology in tree_topology()) {
+            check_exactly_once_delivery(&topology)?;
+        }
+
+        #[test]
+        fn test_broadcast_topology_regular(topology in regular_topology()) {
+            check_exactly_once_delivery(&topology)?;
+        }
+
+        #[test]
+        fn test_broadcast_topology_connected(topology in connected_topology()) {
+            check_exactly_once_delivery(&topology)?;
+        }
     }
 }
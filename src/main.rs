@@ -1,44 +1,257 @@
 #![allow(non_snake_case)]
 
-use std::io::{stdin, stdout};
+use std::{
+    env, fs,
+    io::{stdin, stdout},
+    process,
+    time::Duration,
+};
 
 pub mod packet_handler;
 pub use packet_handler::PacketHandler;
 
+pub mod topology_analysis;
+
 pub mod types;
 pub use types::{
     collection::Collection,
+    delivery_config::DeliveryConfig,
+    id_strategy::{Clock, IdGenerator, IdStrategy, SystemClock, UniqueId},
     message::Message,
     message_handler::MessageHandler,
     message_response::MessageResponse,
-    node_info::{NodeConnectionInfo, NodeInfo},
+    message_store::MessageStore,
+    node_id::{NodeId, NodeKind},
+    node_info::{ClientConnectionInfo, NodeConnectionInfo, NodeInfo},
     packet::Packet,
-    payload::Payload,
-    topology::{BroadcastTopology, Topology},
+    payload::{AddValue, Payload, ReadValue},
+    topology::{
+        BroadcastRedundancy, BroadcastTopology, DisconnectedTopologyPolicy, IndexedTopology,
+        Topology, TopologyStatus, TopologyStrategy,
+    },
+};
+
+pub mod services;
+pub use services::{
+    kv::{KvClient, KvReply, KvRequest},
+    lin_tso::{LinTsoClient, TsReply, TsRequest},
+};
+
+pub mod crdt;
+pub use crdt::{
+    g_counter::GCounter,
+    g_set::GSet,
+    lww_map::LwwMap,
+    lww_register::LwwRegister,
+    mv_register::MvRegister,
+    or_set::OrSet,
+    pn_counter::PnCounter,
+    replicator::{CrdtReplicator, ReplicationMode},
+    two_p_set::TwoPSet,
+    Crdt,
 };
 
+pub mod replication;
+pub use replication::{
+    handler::ReplicatedHandler, ClientRequest, Committed, Consensus, StateMachine,
+};
+
+pub mod raft;
+pub use raft::{
+    consensus::{Raft, RaftRole},
+    log::{LogEntry, RaftLog},
+    RaftHandler,
+};
+
+pub mod paxos;
+pub use paxos::{AcceptedValue, Ballot, MultiPaxos, PaxosHandler, PaxosRole};
+
+pub mod two_phase_commit;
+pub use two_phase_commit::{
+    coordinator::{Coordinator, PendingTxn},
+    participant::Participant,
+    Decision, Resource, TxnId,
+};
+
+pub mod txn;
+pub use txn::{
+    hlc::{HybridClock, Timestamp},
+    mvcc::{Commit, MvccStore, Transaction},
+    MicroOp,
+};
+
+pub mod sharding;
+pub use sharding::{HashRing, Sharded, ShardedHandler};
+
 pub mod handlers;
 pub use handlers::{
-    broadcast_handler::BroadcastHandler, echo_handler::EchoHandler,
+    broadcast_handler::{BroadcastHandler, BroadcastMode},
+    datomic_handler::{DatomicHandler, DatomicTxn},
+    echo_handler::EchoHandler,
+    g_set_handler::{GSetHandler, GSetMode},
     generate_handler::GenerateHandler,
+    lin_kv_handler::{KvStore, LinKvHandler, PaxosLinKvHandler},
+    pn_counter_handler::PnCounterHandler,
+    sharded_kv_handler::{KvHandler, ShardedKvHandler},
+    txn_list_append_handler::{CertifyingTxn, TxnListAppendHandler},
 };
 
+/// Usage: Maelstrom analyze <topology.json> [--dot | --json] [--paths <k>]
+/// Prints the Topology (a JSON object mapping Node Ids to their neighbours), the Broadcast
+/// Topologies built from it and their statistics.
+fn analyze(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: Maelstrom analyze <topology.json> [--dot | --json] [--paths <k>]");
+            process::exit(2);
+        }
+    };
+    let dot = match (
+        args.iter().any(|a| a == "--dot"),
+        args.iter().any(|a| a == "--json"),
+    ) {
+        (true, true) => {
+            eprintln!("Only one of --dot and --json can be set");
+            process::exit(2);
+        }
+        (dot, _) => dot,
+    };
+    let paths = args
+        .iter()
+        .position(|a| a == "--paths")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|k| k.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    let topology: Topology = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+    {
+        Ok(topology) => topology,
+        Err(e) => {
+            eprintln!("Could not read Topology from {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+    let broadcast_topologies = topology_analysis::build_broadcast_topologies(&topology, paths);
+
+    if dot {
+        print!("{}", topology_analysis::topology_to_dot(&topology));
+
+        let mut origins: Vec<&NodeId> = broadcast_topologies.keys().collect();
+        origins.sort();
+        for origin in origins {
+            print!(
+                "{}",
+                topology_analysis::broadcast_topology_to_dot(origin, &broadcast_topologies)
+            );
+        }
+    } else {
+        // NOTE: JSON is exported if --json or no format is set.
+        match topology_analysis::topology_to_json(&topology, &broadcast_topologies) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Could not export Topology: {}", e),
+        }
+    }
+}
+
+/// Usage: Maelstrom [workload]
+/// The workload can also be set using the MAELSTROM_WORKLOAD environment variable, because
+/// Maelstrom does not pass arguments to the binary.
+/// Workloads: "pn-counter", "g-set", "lin-kv", "txn-list-append", everything else runs the
+/// echo, unique-ids and broadcast handlers.
+/// The "datomic" workload runs "txn-list-append" with a transactor over the "lin-kv" and
+/// "lww-kv" services and the "sharded-kv" workload runs "lin-kv" with the keys partitioned over
+/// the Nodes.
+/// The "lin-kv" workload replicates with Raft, unless the MAELSTROM_CONSENSUS environment
+/// variable is set to "paxos".
+/// Broadcasts are forwarded once, unless the MAELSTROM_BROADCAST environment variable is set to
+/// "gossip" or "delta" (see BroadcastMode).
 fn main() {
-    let stdin = stdin();
-    let stdin_lock = stdin.lock();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "analyze") {
+        analyze(&args[1..]);
+        return;
+    }
+    let workload = args
+        .first()
+        .cloned()
+        .or_else(|| env::var("MAELSTROM_WORKLOAD").ok())
+        .unwrap_or_default();
 
     let stdout = stdout();
     let stdout_lock = stdout.lock();
 
-    let mut handler = PacketHandler::new(stdin_lock, stdout_lock);
+    let mut handler = PacketHandler::new(stdin(), stdout_lock);
 
     let mut echo_handler = EchoHandler {};
-    let mut generate_handler = GenerateHandler { counter: 0 };
-    let mut broadcast_handler = BroadcastHandler { messages: vec![] };
+    let mut generate_handler = GenerateHandler::new(IdStrategy::snowflake());
+    let broadcast_mode = match env::var("MAELSTROM_BROADCAST").as_deref() {
+        Ok("gossip") => BroadcastMode::Gossip {
+            interval: Duration::from_millis(500),
+            fanout: 2,
+        },
+        Ok("delta") => BroadcastMode::Delta {
+            interval: Duration::from_millis(500),
+        },
+        _ => BroadcastMode::Forward,
+    };
+    let mut broadcast_handler = BroadcastHandler::new(broadcast_mode);
+    let mut pn_counter_handler = PnCounterHandler::new(Duration::from_millis(500));
+    let mut lin_kv_handler = LinKvHandler::new(
+        Raft::new(Duration::from_millis(500), Duration::from_millis(100)),
+        KvStore::default(),
+    );
+    lin_kv_handler.set_snapshot_threshold(1000);
+    let mut paxos_lin_kv_handler = PaxosLinKvHandler::new(
+        MultiPaxos::new(Duration::from_millis(500), Duration::from_millis(100)),
+        KvStore::default(),
+    );
+    let mut txn_list_append_handler =
+        TxnListAppendHandler::new(HybridClock::new(), Duration::from_millis(500));
+    let mut datomic_handler =
+        DatomicHandler::new(IdStrategy::snowflake(), Duration::from_millis(1000));
+    let mut sharded_kv_handler =
+        ShardedKvHandler::new(KvHandler::new(Duration::from_millis(500)), 32);
+    let mut g_set_handler = GSetHandler::new(GSetMode::State {
+        replication: ReplicationMode::Delta,
+        interval: Duration::from_millis(500),
+    });
 
-    handler.add_handler(&mut echo_handler);
-    handler.add_handler(&mut generate_handler);
-    handler.add_handler(&mut broadcast_handler);
+    // NOTE: Workloads with the same Message types (e.g. "read") can not be run together.
+    match workload.as_str() {
+        "pn-counter" => {
+            handler.add_handler(&mut pn_counter_handler);
+        }
+        "g-set" => {
+            handler.add_handler(&mut g_set_handler);
+        }
+        "lin-kv" if env::var("MAELSTROM_CONSENSUS").is_ok_and(|c| c == "paxos") => {
+            handler.add_handler(&mut paxos_lin_kv_handler);
+        }
+        "lin-kv" => {
+            handler.add_handler(&mut lin_kv_handler);
+        }
+        "txn-list-append" => {
+            handler.add_handler(&mut txn_list_append_handler);
+        }
+        "datomic" => {
+            handler.add_handler(&mut datomic_handler);
+        }
+        "sharded-kv" => {
+            handler.add_handler(&mut sharded_kv_handler);
+        }
+        _ => {
+            handler.add_handler(&mut echo_handler);
+            handler.add_handler(&mut generate_handler);
+            handler.add_handler(&mut broadcast_handler);
+        }
+    }
+    handler.set_tick_interval(Duration::from_millis(100));
+    let mut delivery = DeliveryConfig::default();
+    delivery.set_resend_timeout(Duration::from_millis(500));
+    handler.set_delivery_config(delivery);
 
     handler.run();
 }
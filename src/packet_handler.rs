@@ -1,53 +1,110 @@
 #![allow(unused)]
 
 use debug_print::{debug_eprint, debug_eprintln};
-use serde_json::de::{IoRead, StreamDeserializer};
 use std::{
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     io::{Read, Write},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::types::{
     collection::Collection,
-    helpers::build_broadcast_topology,
+    delivery_config::DeliveryConfig,
+    helpers::{
+        build_broadcast_topology, build_redundant_broadcast_topology, build_topology,
+        connected_components, repair_topology,
+    },
     message::Message,
     message_handler::MessageHandler,
     message_response::MessageResponse,
-    node_info::{MessageSyncStatus, NodeConnectionInfo, NodeInfo},
+    node_id::NodeId,
+    node_info::{ClientConnectionInfo, MessageSyncStatus, NodeConnectionInfo, NodeInfo},
     packet::Packet,
     payload::Payload,
+    topology::{
+        BroadcastRedundancy, DisconnectedTopologyPolicy, IndexedTopology, Topology, TopologyStatus,
+        TopologyStrategy,
+    },
 };
 
-pub struct PacketHandler<'de, 'a, I, O>
+pub struct PacketHandler<'a, O>
 where
-    I: Read,
     O: Write,
 {
-    stdin: StreamDeserializer<'de, IoRead<I>, Packet>,
+    stdin: Receiver<serde_json::Result<Packet>>,
     stdout: O,
     state: Option<NodeInfo>,
     handlers: Vec<&'a mut dyn MessageHandler>,
+    delivery: DeliveryConfig,
+    topology_strategy: TopologyStrategy,
+    disconnected_topology_policy: DisconnectedTopologyPolicy,
+    broadcast_redundancy: BroadcastRedundancy,
+    /// Interval in which the handlers are ticked, no ticks are generated if None.
+    tick_interval: Option<Duration>,
+    next_tick: Instant,
+    /// When the last Heartbeats have been sent (only used with a suspect timeout).
+    last_heartbeat: Instant,
+    /// The Node that forwarded a request to this Node by the Client and msg_id of the request,
+    /// so the reply can be relayed back through it.
+    proxied_requests: HashMap<(NodeId, usize), NodeId>,
 }
 
-impl<'de, 'a, I, O> PacketHandler<'de, 'a, I, O>
+impl<'a, O> PacketHandler<'a, O>
 where
-    I: Read,
     O: Write,
 {
-    pub fn new(stdin: I, stdout: O) -> Self {
-        let reader = serde_json::Deserializer::from_reader(stdin);
-        let stdin = reader.into_iter();
+    pub fn new<I>(stdin: I, stdout: O) -> Self
+    where
+        I: Read + Send + 'static,
+    {
+        // NOTE: Packets are parsed on a separate thread, so the handlers can be ticked while
+        // no Packets arrive.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let reader = serde_json::Deserializer::from_reader(stdin);
+            for packet in reader.into_iter::<Packet>() {
+                if sender.send(packet).is_err() {
+                    break;
+                }
+            }
+        });
 
         Self {
-            stdin,
+            stdin: receiver,
             stdout,
             state: Option::None,
             handlers: vec![],
+            delivery: Default::default(),
+            topology_strategy: Default::default(),
+            disconnected_topology_policy: Default::default(),
+            broadcast_redundancy: Default::default(),
+            tick_interval: None,
+            next_tick: Instant::now(),
+            last_heartbeat: Instant::now(),
+            proxied_requests: HashMap::new(),
         }
     }
+    pub fn set_delivery_config(&mut self, delivery: DeliveryConfig) {
+        self.delivery = delivery;
+    }
+    pub fn set_topology_strategy(&mut self, topology_strategy: TopologyStrategy) {
+        self.topology_strategy = topology_strategy;
+    }
+    pub fn set_disconnected_topology_policy(&mut self, policy: DisconnectedTopologyPolicy) {
+        self.disconnected_topology_policy = policy;
+    }
+    pub fn set_broadcast_redundancy(&mut self, broadcast_redundancy: BroadcastRedundancy) {
+        self.broadcast_redundancy = broadcast_redundancy;
+    }
+    pub fn set_tick_interval(&mut self, tick_interval: Duration) {
+        self.tick_interval = Some(tick_interval);
+        self.next_tick = Instant::now() + tick_interval;
+    }
     fn init(&mut self) {
-        if let Some(Ok(Packet {
+        if let Ok(Ok(Packet {
             src,
             body:
                 Message {
@@ -59,30 +116,34 @@ where
                     ..
                 },
             ..
-        })) = self.stdin.next()
+        })) = self.stdin.recv()
         {
             debug_eprintln!("Got Init Message");
 
-            let node_number = node_id[1..].parse::<usize>().unwrap();
+            // NOTE: Node Ids without a number are numbered by their position in the Node Ids.
+            let node_number = node_id.number().unwrap_or_else(|| {
+                let mut sorted_ids = node_ids.clone();
+                sorted_ids.sort();
+                sorted_ids.iter().position(|n| *n == node_id).unwrap_or(0)
+            });
             let client_nodes = node_ids
                 .iter()
-                .filter(|id| id.starts_with('c'))
+                .filter(|id| id.is_client())
                 .cloned()
                 .collect();
-            let server_nodes: Vec<String> = node_ids
+            let server_nodes: Vec<NodeId> = node_ids
                 .iter()
-                .filter(|id| id.starts_with('n'))
+                .filter(|id| id.is_server())
                 .cloned()
                 .collect();
 
             let mut conn_info = HashMap::new();
             for node in server_nodes.iter() {
                 conn_info.insert(
-                    node.clone(),
+                    *node,
                     NodeConnectionInfo {
-                        out_msg_id: 0,
-                        in_msg_id: MessageSyncStatus::Synced { last_msg_id: 0 },
-                        un_ack_messages: Default::default(),
+                        last_heard: Some(Instant::now()),
+                        ..Default::default()
                     },
                 );
             }
@@ -95,12 +156,16 @@ where
                 client_nodes,
                 server_nodes,
                 topology: Default::default(),
+                indexed_topology: Default::default(),
+                topology_status: Default::default(),
                 broadcast_topology: Default::default(),
+                suspected_nodes: Default::default(),
                 conn_info,
+                client_conn_info: Default::default(),
             });
 
             let ok_packet = Packet {
-                src: self.state.as_ref().unwrap().node_id.clone(),
+                src: self.state.as_ref().unwrap().node_id,
                 dest: src,
                 body: Message {
                     in_reply_to: msg_id,
@@ -118,85 +183,375 @@ where
     }
     pub fn step(&mut self) {
         if self.state.is_some() {
-            match self.stdin.next() {
+            match self.receive_packet() {
                 Some(Ok(packet)) => {
                     debug_eprintln!("Got {:#?}", packet);
 
-                    let mut send_sync_request = false;
-                    if let Some(mut conn_info) = self.get_state_mut().conn_info.get_mut(&packet.src)
-                    {
-                        if let Some(msg_id) = packet.body.msg_id {
-                            match conn_info.in_msg_id.is_next_msg_id(msg_id) {
-                                // NOTE: If packet msg_id is lower than the expected one,
-                                // this packet has already been received => immidiately return.
-                                Ordering::Less => return,
-                                Ordering::Equal => conn_info.in_msg_id.increment_msg_id(),
-                                // NOTE: If packet msg_id is higher than the expected one,
-                                // some packets have not been received => Add to missing msg_ids
-                                // and send sync request to source node.
-                                Ordering::Greater => {
-                                    conn_info.in_msg_id.add_missing_msg_ids(msg_id);
-                                    send_sync_request = true;
-                                }
-                            }
+                    if let Some(responses) = self.replay_client_request(&packet) {
+                        for response in responses {
+                            Self::write_packet_inner(self.stdout.by_ref(), &response);
                         }
+                        return;
+                    }
 
-                        if let Some(in_reply_to) = packet.body.in_reply_to {
-                            Self::ack_packet_inner(conn_info, in_reply_to);
-                        }
+                    let (deliverable, mut packets) = self.track_incoming(packet);
+                    for packet in deliverable {
+                        packets += self.handle_packet(packet);
                     }
+                    self.write_packets(packets);
+                }
+                Some(Err(e)) => eprintln!("Error parsing Message {}", e),
+                None => {}
+            }
+
+            if self.tick_interval.is_some() && self.next_tick <= Instant::now() {
+                self.tick();
+            }
+        } else {
+            self.init();
+        }
+    }
+    /// Waits for the next Packet until the next tick is due.
+    /// Returns None if the tick is due before a Packet arrives.
+    fn receive_packet(&mut self) -> Option<serde_json::Result<Packet>> {
+        let packet = match self.tick_interval {
+            Some(_) => self
+                .stdin
+                .recv_timeout(self.next_tick.saturating_duration_since(Instant::now())),
+            None => self
+                .stdin
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match packet {
+            Ok(packet) => Some(packet),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => panic!("No more Messages!"),
+        }
+    }
+    /// Ticks the handlers and resends overdue un-acked Messages.
+    pub(crate) fn tick(&mut self) {
+        if let Some(tick_interval) = self.tick_interval {
+            self.next_tick = Instant::now() + tick_interval;
+        }
+
+        self.update_suspected_nodes();
+
+        let mut handlers = std::mem::take(&mut self.handlers);
+        let mut packets: Vec<Packet> = self.overdue_packets();
+        packets.extend(self.heartbeats());
+
+        for handler in handlers.iter_mut() {
+            let responses: Vec<MessageResponse> = handler.handle_tick(self.get_state()).into();
+            packets.extend(
+                responses
+                    .into_iter()
+                    .filter_map(|r| self.create_packet(None, r)),
+            );
+        }
+
+        let _ = std::mem::replace(&mut self.handlers, handlers);
+
+        self.write_packets(packets.into());
+    }
+    /// Returns the un-acked Messages that have been waiting for their Ack longer than the
+    /// resend timeout.
+    fn overdue_packets(&self) -> Vec<Packet> {
+        let resend_timeout = match self.delivery.resend_timeout {
+            Some(resend_timeout) => resend_timeout,
+            None => return Vec::with_capacity(0),
+        };
+
+        let state = self.get_state();
+        state
+            .conn_info
+            .iter()
+            .flat_map(|(node, conn_info)| {
+                conn_info
+                    .un_ack_messages
+                    .iter()
+                    .filter(|(_, sent)| sent.elapsed() >= resend_timeout)
+                    .map(|(message, _)| Packet {
+                        src: state.node_id,
+                        dest: *node,
+                        body: message.clone(),
+                    })
+            })
+            .collect()
+    }
+    /// Returns the Heartbeats to all other Server Nodes if they are due.
+    fn heartbeats(&mut self) -> Vec<Packet> {
+        let suspect_timeout = match self.broadcast_redundancy.suspect_timeout {
+            Some(suspect_timeout) => suspect_timeout,
+            None => return Vec::with_capacity(0),
+        };
+        if self.last_heartbeat.elapsed() < suspect_timeout / 3 {
+            return Vec::with_capacity(0);
+        }
+        self.last_heartbeat = Instant::now();
+
+        // NOTE: Only the Nodes this Node has not heard from itself are sent, otherwise the
+        // Nodes would keep suspecting each other's suspicions after a Node recovered.
+        let mut suspected: Vec<NodeId> = self.silent_nodes(suspect_timeout).into_iter().collect();
+        suspected.sort();
+        let state = self.get_state();
+        state
+            .server_nodes
+            .iter()
+            .filter(|n| **n != state.node_id)
+            .map(|n| Packet {
+                src: state.node_id,
+                dest: *n,
+                body: Message {
+                    msg_id: None,
+                    in_reply_to: None,
+                    payload: Payload::Heartbeat {
+                        suspected: suspected.clone(),
+                    },
+                },
+            })
+            .collect()
+    }
+    /// Returns the other Server Nodes that have not sent anything for the suspect timeout.
+    fn silent_nodes(&self, suspect_timeout: Duration) -> HashSet<NodeId> {
+        let state = self.get_state();
+        state
+            .conn_info
+            .iter()
+            .filter(|(n, _)| **n != state.node_id)
+            .filter(|(_, c)| c.last_heard.is_none_or(|t| t.elapsed() > suspect_timeout))
+            .map(|(n, _)| *n)
+            .collect()
+    }
+    /// Suspects the Server Nodes that have not sent anything for the suspect timeout and the
+    /// Nodes suspected by the other Nodes and rebuilds the Broadcast Topology if the suspected
+    /// Nodes changed.
+    fn update_suspected_nodes(&mut self) {
+        let suspect_timeout = match self.broadcast_redundancy.suspect_timeout {
+            Some(suspect_timeout) => suspect_timeout,
+            None => return,
+        };
+
+        let silent = self.silent_nodes(suspect_timeout);
+        let state = self.get_state();
+        // NOTE: This Node is never suspected by itself, otherwise it would not forward anything.
+        let suspected_nodes: HashSet<NodeId> = state
+            .conn_info
+            .iter()
+            .filter(|(n, _)| !silent.contains(*n))
+            .flat_map(|(_, c)| c.suspected.iter())
+            .chain(silent.iter())
+            .filter(|n| **n != state.node_id)
+            .cloned()
+            .collect();
+
+        if suspected_nodes != state.suspected_nodes {
+            debug_eprintln!("Suspected Nodes changed to {:?}", suspected_nodes);
+            self.get_state_mut().suspected_nodes = suspected_nodes;
+            self.rebuild_broadcast_topology();
+        }
+    }
+    fn rebuild_broadcast_topology(&mut self) {
+        let paths = self.broadcast_redundancy.paths;
+        let state = self.get_state_mut();
+
+        // NOTE: Suspected Nodes keep their entry, but all links to them are removed, so the
+        // Broadcasts are sent along backup paths.
+        let filtered;
+        let topology = match state.suspected_nodes.is_empty() {
+            true => &state.indexed_topology,
+            false => {
+                filtered = state
+                    .indexed_topology
+                    .without_links_to(&state.suspected_nodes);
+                &filtered
+            }
+        };
 
-                    let mut packets = match send_sync_request {
-                        true => Collection::One(Packet {
-                            src: packet.dest.clone(),
-                            dest: packet.src.clone(),
-                            body: Message {
-                                msg_id: None,
-                                in_reply_to: None,
-                                payload: Payload::SyncRequest,
-                            },
+        state.broadcast_topology = match paths {
+            0 | 1 => build_broadcast_topology(&state.node_id, topology),
+            paths => build_redundant_broadcast_topology(&state.node_id, topology, paths),
+        };
+
+        debug_eprintln!("Built Broadcast Topology: {:#?}", state.broadcast_topology);
+    }
+    /// Wraps replies to proxied requests into a Forward to the proxy, which relays them to the
+    /// Client as if it had handled the request itself.
+    fn relay_proxied(&mut self, packet: Packet) -> Packet {
+        let proxy = match packet.body.in_reply_to {
+            Some(in_reply_to) => self.proxied_requests.remove(&(packet.dest, in_reply_to)),
+            None => None,
+        };
+        match proxy {
+            Some(proxy) => Packet {
+                src: packet.src,
+                dest: proxy,
+                body: Message {
+                    msg_id: None,
+                    in_reply_to: None,
+                    payload: Payload::Forward {
+                        packet: Box::new(Packet {
+                            src: proxy,
+                            ..packet
                         }),
-                        false => Collection::None,
-                    };
-                    packets += self.handle_packet(packet);
-                    match packets {
-                        Collection::None => {}
-                        Collection::One(packet) => {
-                            self.write_packet(packet);
-                        }
-                        Collection::Multiple(packets) => {
-                            let dict: HashMap<String, Vec<Message>> =
-                                packets.into_iter().fold(HashMap::new(), |mut acc, packet| {
-                                    acc.entry(packet.dest).or_default().push(packet.body);
-                                    acc
-                                });
-
-                            for kvp in dict {
-                                let (dest, messages) = kvp;
-                                match messages.len() {
-                                    0 => {}
-                                    1 => self.write_packet(Packet {
-                                        src: self.get_node_id().clone(),
-                                        dest,
-                                        body: messages.into_iter().next().unwrap(),
-                                    }),
-                                    _ => self.write_batch(dest, messages),
-                                }
+                    },
+                },
+            },
+            None => packet,
+        }
+    }
+    fn write_packets(&mut self, packets: Collection<Packet>) {
+        let packets = match self.proxied_requests.is_empty() {
+            true => packets,
+            false => Vec::from(packets)
+                .into_iter()
+                .map(|p| self.relay_proxied(p))
+                .collect::<Vec<Packet>>()
+                .into(),
+        };
+        match packets {
+            Collection::None => {}
+            Collection::One(packet) => {
+                self.write_packet(packet);
+            }
+            Collection::Multiple(packets) => {
+                // NOTE: Group by "src" and "dest", because some Packets are sent with a foreign
+                // "src" Address (e.g. forwarded Broadcasts).
+                let dict: HashMap<(NodeId, NodeId), Vec<Message>> =
+                    packets.into_iter().fold(HashMap::new(), |mut acc, packet| {
+                        acc.entry((packet.src, packet.dest))
+                            .or_default()
+                            .push(packet.body);
+                        acc
+                    });
+
+                for kvp in dict {
+                    let ((src, dest), messages) = kvp;
+                    // NOTE: Only Server Nodes understand Batches, Services and Clients get
+                    // every Message in its own Packet.
+                    match (messages.len(), dest.is_server()) {
+                        (0, _) => {}
+                        (1, _) | (_, false) => {
+                            for body in messages {
+                                self.write_packet(Packet { src, dest, body });
                             }
                         }
+                        _ => self.write_batch(src, dest, messages),
                     }
                 }
-                Some(Err(e)) => eprintln!("Error parsing Message {}", e),
-                None => panic!("No more Messages!"),
             }
-        } else {
-            self.init();
         }
     }
-    fn write_batch(&mut self, dest: String, messages: Vec<Message>) {
+    /// Checks if a Client request has already been handled when exactly-once delivery is
+    /// enabled.
+    /// Returns the responses that were sent for the first request, which should be resent
+    /// instead of handling the request again.
+    fn replay_client_request(&mut self, packet: &Packet) -> Option<Vec<Packet>> {
+        let msg_id = match (self.delivery.exactly_once, packet.body.msg_id) {
+            (true, Some(msg_id)) if packet.src.is_client() => msg_id,
+            _ => return None,
+        };
+        let window = self.delivery.client_window;
+        let client_info = self
+            .get_state_mut()
+            .client_conn_info
+            .entry(packet.src)
+            .or_default();
+
+        match client_info.get_responses(msg_id) {
+            // NOTE: The request is still being handled, its responses are sent once it has
+            // been handled, so the retry is dropped instead of being handled again.
+            Some(responses) if responses.is_empty() => {
+                debug_eprintln!("Dropping retry of request {} of {}", msg_id, packet.src);
+                Some(Vec::with_capacity(0))
+            }
+            Some(responses) => {
+                debug_eprintln!("Replaying request {} of {}", msg_id, packet.src);
+                Some(responses.clone())
+            }
+            None => {
+                client_info.add_request(msg_id, window);
+                None
+            }
+        }
+    }
+    /// Updates the Connection Info of the source Node for an incoming Message.
+    /// Returns the Packets that should be handled now and the Packets (Ack and SyncRequest)
+    /// that have to be sent back to the source Node.
+    fn track_incoming(&mut self, packet: Packet) -> (Vec<Packet>, Collection<Packet>) {
+        let node_id = *self.get_node_id();
+        let fifo = self.delivery.is_fifo();
+        let conn_info = match self.get_state_mut().conn_info.get_mut(&packet.src) {
+            Some(conn_info) => conn_info,
+            None => return (vec![packet], Collection::None),
+        };
+        // NOTE: Forwarded Packets keep the "src" of their origin and are sent without msg_id,
+        // so only Packets that the source Node sent itself are credited to it.
+        if packet.body.msg_id.is_some() || matches!(packet.body.payload, Payload::Heartbeat { .. })
+        {
+            conn_info.last_heard = Some(Instant::now());
+        }
+
+        if let Some(in_reply_to) = packet.body.in_reply_to {
+            Self::ack_packet_inner(conn_info, in_reply_to);
+        }
+
+        let msg_id = match packet.body.msg_id {
+            Some(msg_id) => msg_id,
+            None => return (vec![packet], Collection::None),
+        };
+
+        let (deliver, mut missing_msg_ids) = match conn_info.in_msg_id.is_next_msg_id(msg_id) {
+            // NOTE: If packet msg_id is lower than the expected one, it is either a resent
+            // missing message or it has already been received => only handle missing ones.
+            Ordering::Less => (conn_info.in_msg_id.remove_missing_msg_id(msg_id), None),
+            Ordering::Equal => {
+                conn_info.in_msg_id.increment_msg_id();
+                (true, None)
+            }
+            // NOTE: If packet msg_id is higher than the expected one,
+            // some packets have not been received => Add to missing msg_ids
+            // and send sync request with the missing msg_ids to source node.
+            Ordering::Greater => {
+                conn_info.in_msg_id.add_missing_msg_ids(msg_id);
+                (true, Some(conn_info.in_msg_id.get_missing_msg_ids()))
+            }
+        };
+
+        // NOTE: FIFO delivery is blocked until the gap is filled, so the missing msg_ids are
+        // requested again for every Message that arrives while a gap exists.
+        if fifo && missing_msg_ids.is_none() && !conn_info.in_msg_id.is_synced() {
+            missing_msg_ids = Some(conn_info.in_msg_id.get_missing_msg_ids());
+        }
+
+        let src = packet.src;
+        let deliverable = match (deliver, fifo) {
+            (false, _) => Vec::with_capacity(0),
+            (true, false) => vec![packet],
+            (true, true) => conn_info.release_in_order(msg_id, packet),
+        };
+
+        // NOTE: Duplicates are acked as well, because the first Ack might have been lost.
+        let mut packets = Collection::One(self.create_ack(src, msg_id));
+        if let Some(msg_ids) = missing_msg_ids {
+            packets += Collection::One(Packet {
+                src: node_id,
+                dest: src,
+                body: Message {
+                    msg_id: None,
+                    in_reply_to: None,
+                    payload: Payload::SyncRequest { msg_ids },
+                },
+            });
+        }
+
+        (deliverable, packets)
+    }
+    fn write_batch(&mut self, src: NodeId, dest: NodeId, messages: Vec<Message>) {
         let packet = Packet {
-            src: self.get_node_id().clone(),
-            dest: dest.clone(),
+            src,
+            dest,
             body: Message {
                 msg_id: None,
                 in_reply_to: None,
@@ -207,12 +562,14 @@ where
 
         match packet.body.payload {
             Payload::Batch { messages } => {
-                for message in messages.into_iter().filter(|m| m.msg_id.is_some()) {
-                    self.add_packet_to_ack(Packet {
-                        src: self.get_node_id().clone(),
-                        dest: dest.clone(),
+                for message in messages {
+                    let packet = Packet {
+                        src,
+                        dest,
                         body: message,
-                    });
+                    };
+                    self.add_response_to_replay(&packet);
+                    self.add_packet_to_ack(packet);
                 }
             }
             _ => panic!("How did this happen?"),
@@ -221,12 +578,12 @@ where
     fn create_error_packet(
         &self,
         in_reply_to: Option<usize>,
-        dest: String,
+        dest: NodeId,
         code: usize,
         text: String,
     ) -> Packet {
         Packet {
-            src: self.get_node_id().clone(),
+            src: *self.get_node_id(),
             dest,
             body: Message {
                 msg_id: None,
@@ -252,8 +609,22 @@ where
                 14,
                 "Got second Init Message".to_string(),
             )),
-            // NOTE: Single Ack Packets are acked using their in_reply_to Field in the step
-            // function.
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::Heartbeat { suspected },
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(conn_info) = self.get_state_mut().conn_info.get_mut(&src) {
+                    conn_info.suspected = suspected.into_iter().collect();
+                }
+                Collection::None
+            }
+            // NOTE: Single Ack Packets are acked using their in_reply_to Field in the
+            // track_incoming function.
             Packet {
                 body:
                     Message {
@@ -272,17 +643,53 @@ where
                     },
                 ..
             } => {
+                debug_eprintln!("Got Topology: {:#?}", topology);
+
                 // NOTE: Rebuilding the Topology every time a Topology Packet is sent is fine.
+                let topology_strategy = self.topology_strategy.clone();
+                let policy = self.disconnected_topology_policy.clone();
                 let state = self.get_state_mut();
-                state.broadcast_topology =
-                    build_broadcast_topology(&state.node_id, &state.server_nodes, &topology);
+                let mut topology =
+                    build_topology(&topology_strategy, &state.server_nodes, &topology);
+
+                let components = connected_components(&state.server_nodes, &topology);
+                if components.len() > 1 {
+                    debug_eprintln!("Topology is disconnected: {:?}", components);
+                    match policy {
+                        DisconnectedTopologyPolicy::Reject => {
+                            state.topology_status = TopologyStatus::Rejected { components };
+                            return Collection::One(self.create_error_packet(
+                                msg_id,
+                                src,
+                                12,
+                                "Topology is disconnected".to_string(),
+                            ));
+                        }
+                        DisconnectedTopologyPolicy::Repair => {
+                            let bridges = repair_topology(&mut topology, &components);
+                            debug_eprintln!("Repaired Topology with {:?}", bridges);
+                            state.topology_status = TopologyStatus::Repaired {
+                                components,
+                                bridges,
+                            };
+                        }
+                    }
+                } else {
+                    state.topology_status = TopologyStatus::Connected;
+                }
+
+                state.indexed_topology = IndexedTopology::new(&state.server_nodes, &topology);
                 state.topology = topology;
+                debug_eprintln!(
+                    "Built {:?} Topology: {:#?}",
+                    topology_strategy,
+                    state.topology
+                );
 
-                debug_eprintln!("Got Topology: {:#?}", state.topology);
-                debug_eprintln!("Built Broadcast Topology: {:#?}", state.broadcast_topology);
+                self.rebuild_broadcast_topology();
 
                 Collection::One(Packet {
-                    src: self.get_node_id().clone(),
+                    src: *self.get_node_id(),
                     dest: src,
                     body: Message {
                         msg_id: None,
@@ -291,6 +698,22 @@ where
                     },
                 })
             }
+            // NOTE: Forwarded Packets addressed to this Node are requests proxied by another
+            // Node, all other Forwarded Packets are relayed to their destination.
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::Forward { packet },
+                        ..
+                    },
+                ..
+            } if &packet.dest == self.get_node_id() => {
+                if let Some(msg_id) = packet.body.msg_id {
+                    self.proxied_requests.insert((packet.src, msg_id), src);
+                }
+                self.handle_packet(*packet)
+            }
             Packet {
                 body:
                     Message {
@@ -303,29 +726,28 @@ where
                 src,
                 body:
                     Message {
-                        payload: Payload::SyncRequest,
+                        payload: Payload::SyncRequest { msg_ids },
                         ..
                     },
                 ..
             } => {
-                if let Some(messages) = self
-                    .get_state_mut()
-                    .conn_info
-                    .get_mut(&src)
-                    .map(|m| &mut m.un_ack_messages)
-                {
-                    let messages = std::mem::replace(messages, Vec::with_capacity(0));
-                    messages
-                        .into_iter()
+                // NOTE: Only the requested messages are resent and they stay in the un-acked
+                // queue, so a lost resend can be requested again.
+                let msg_ids: HashSet<usize> = msg_ids.into_iter().collect();
+                match self.get_state().conn_info.get(&src) {
+                    Some(conn_info) => conn_info
+                        .un_ack_messages
+                        .iter()
+                        .map(|(m, _)| m)
+                        .filter(|m| m.msg_id.is_some_and(|id| msg_ids.contains(&id)))
                         .map(|m| Packet {
-                            src: self.get_node_id().clone(),
-                            dest: src.clone(),
-                            body: m,
+                            src: *self.get_node_id(),
+                            dest: src,
+                            body: m.clone(),
                         })
                         .collect::<Vec<Packet>>()
-                        .into()
-                } else {
-                    Collection::None
+                        .into(),
+                    None => Collection::None,
                 }
             }
             Packet {
@@ -341,14 +763,18 @@ where
 
                 for message in messages {
                     let packet = Packet {
-                        src: src.clone(),
-                        dest: dest.clone(),
+                        src,
+                        dest,
                         body: message,
                     };
-                    match self.handle_packet(packet) {
-                        Collection::None => {}
-                        Collection::One(packet) => responses.push(packet),
-                        Collection::Multiple(packets) => responses.extend(packets),
+                    let (deliverable, packets) = self.track_incoming(packet);
+                    responses.extend(Vec::from(packets));
+                    for packet in deliverable {
+                        match self.handle_packet(packet) {
+                            Collection::None => {}
+                            Collection::One(packet) => responses.push(packet),
+                            Collection::Multiple(packets) => responses.extend(packets),
+                        }
                     }
                 }
 
@@ -381,12 +807,12 @@ where
                     match responses {
                         Collection::None => {}
                         Collection::One(response) => {
-                            packets.push(self.create_packet(&packet, response))
+                            packets.extend(self.create_packet(Some(&packet), response))
                         }
                         Collection::Multiple(responses) => packets.extend(
                             responses
                                 .into_iter()
-                                .map(|r| self.create_packet(&packet, r)),
+                                .filter_map(|r| self.create_packet(Some(&packet), r)),
                         ),
                     };
                 }
@@ -402,7 +828,12 @@ where
             self.step();
         }
     }
-    fn create_packet(&mut self, trigger: &Packet, response: MessageResponse) -> Packet {
+    /// Returns None for Responses without a received Packet (e.g. from handle_tick).
+    fn create_packet(
+        &mut self,
+        trigger: Option<&Packet>,
+        response: MessageResponse,
+    ) -> Option<Packet> {
         match response {
             MessageResponse::Ack {
                 src,
@@ -410,12 +841,14 @@ where
                 in_reply_to,
                 payload,
             } => {
-                let src = src.unwrap_or_else(|| self.get_state().node_id.clone());
-                let msg_id = self.get_state_mut().conn_info.get_mut(&src).map(|o| {
-                    o.out_msg_id += 1;
-                    o.out_msg_id
-                });
-                Packet {
+                let src = src.unwrap_or_else(|| self.get_state().node_id);
+                // NOTE: The receiver tracks msg_ids by the "src" Address, so Packets with a
+                // foreign "src" can not be tracked and are sent without msg_id.
+                let msg_id = match &src == self.get_node_id() {
+                    true => self.next_msg_id(&dest),
+                    false => None,
+                };
+                Some(Packet {
                     src,
                     dest,
                     body: Message {
@@ -423,7 +856,7 @@ where
                         in_reply_to,
                         payload,
                     },
-                }
+                })
             }
             MessageResponse::NoAck {
                 src,
@@ -431,8 +864,8 @@ where
                 in_reply_to,
                 payload,
             } => {
-                let src = src.unwrap_or_else(|| self.get_state().node_id.clone());
-                Packet {
+                let src = src.unwrap_or_else(|| self.get_state().node_id);
+                Some(Packet {
                     src,
                     dest,
                     body: Message {
@@ -440,12 +873,16 @@ where
                         in_reply_to,
                         payload,
                     },
-                }
+                })
             }
             MessageResponse::Response { payload } => {
-                let src = trigger.dest.clone();
-                let dest = trigger.src.clone();
-                Packet {
+                let Some(trigger) = trigger else {
+                    debug_eprintln!("Dropped Response without a received Packet: {:?}", payload);
+                    return None;
+                };
+                let src = trigger.dest;
+                let dest = trigger.src;
+                Some(Packet {
                     src,
                     dest,
                     body: Message {
@@ -453,17 +890,17 @@ where
                         msg_id: None,
                         payload,
                     },
-                }
+                })
             }
             MessageResponse::ResponseWithAck { payload } => {
-                let src = trigger.dest.clone();
-                let dest = trigger.src.clone();
-                let msg_id = self.get_state_mut().conn_info.get_mut(&src).map(|o| {
-                    let id = o.out_msg_id;
-                    o.out_msg_id += 1;
-                    id
-                });
-                Packet {
+                let Some(trigger) = trigger else {
+                    debug_eprintln!("Dropped Response without a received Packet: {:?}", payload);
+                    return None;
+                };
+                let src = trigger.dest;
+                let dest = trigger.src;
+                let msg_id = self.next_msg_id(&dest);
+                Some(Packet {
                     src,
                     dest,
                     body: Message {
@@ -471,16 +908,35 @@ where
                         msg_id,
                         payload,
                     },
-                }
+                })
             }
+            MessageResponse::Request {
+                dest,
+                msg_id,
+                payload,
+            } => Some(Packet {
+                src: self.get_state().node_id,
+                dest,
+                body: Message {
+                    msg_id: Some(msg_id),
+                    in_reply_to: None,
+                    payload,
+                },
+            }),
         }
     }
-    pub fn get_node_id(&self) -> &String {
+    fn next_msg_id(&mut self, dest: &NodeId) -> Option<usize> {
+        self.get_state_mut().conn_info.get_mut(dest).map(|o| {
+            o.out_msg_id += 1;
+            o.out_msg_id
+        })
+    }
+    pub fn get_node_id(&self) -> &NodeId {
         &self.get_state().node_id
     }
-    pub fn create_ack(&self, dest: String, msg_id: usize) -> Packet {
+    pub fn create_ack(&self, dest: NodeId, msg_id: usize) -> Packet {
         Packet {
-            src: self.get_state().node_id.clone(),
+            src: self.get_state().node_id,
             dest,
             body: Message {
                 msg_id: None,
@@ -492,12 +948,30 @@ where
     pub fn write_packet(&mut self, packet: Packet) {
         Self::write_packet_inner(self.stdout.by_ref(), &packet);
 
+        self.add_response_to_replay(&packet);
         self.add_packet_to_ack(packet);
     }
+    fn add_response_to_replay(&mut self, packet: &Packet) {
+        if self.delivery.exactly_once {
+            if let Some(client_info) = self.get_state_mut().client_conn_info.get_mut(&packet.dest) {
+                client_info.add_response(packet);
+            }
+        }
+    }
     fn add_packet_to_ack(&mut self, packet: Packet) {
         if packet.body.msg_id.is_some() {
-            if let Some(conn_info) = self.state.as_mut().unwrap().conn_info.get_mut(&packet.src) {
-                conn_info.un_ack_messages.push(packet.body);
+            if let Some(conn_info) = self.state.as_mut().unwrap().conn_info.get_mut(&packet.dest) {
+                // NOTE: Resent messages are already waiting for their Ack.
+                match conn_info
+                    .un_ack_messages
+                    .iter_mut()
+                    .find(|(m, _)| m.msg_id == packet.body.msg_id)
+                {
+                    Some((_, sent)) => *sent = Instant::now(),
+                    None => conn_info
+                        .un_ack_messages
+                        .push((packet.body, Instant::now())),
+                }
             }
         }
     }
@@ -508,10 +982,10 @@ where
         debug_eprintln!("Send {:#?}", packet);
 
         let _ = serde_json::to_writer(stdout.by_ref(), &packet);
-        let _ = stdout.write(&[b'\n']);
+        let _ = stdout.write(b"\n");
         let _ = stdout.flush();
     }
-    fn ack_packet(&mut self, src: &String, msg_id: usize) -> bool {
+    fn ack_packet(&mut self, src: &NodeId, msg_id: usize) -> bool {
         match self.get_state_mut().conn_info.get_mut(src) {
             Some(conn_info) => Self::ack_packet_inner(conn_info, msg_id),
             None => false,
@@ -527,7 +1001,7 @@ where
         match conn_info
             .un_ack_messages
             .iter()
-            .position(|m| m.msg_id.map_or(false, |id| id == msg_id))
+            .position(|(m, _)| m.msg_id == Some(msg_id))
         {
             Some(idx) => {
                 conn_info.un_ack_messages.swap_remove(idx);
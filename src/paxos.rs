@@ -0,0 +1,518 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    replication::{handler::ReplicatedHandler, Committed, Consensus},
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_id::NodeId, node_info::NodeInfo, packet::Packet,
+        payload::Payload,
+    },
+};
+
+/// Makes a StateMachine linearizable by replicating its requests with Multi-Paxos.
+pub type PaxosHandler<S> = ReplicatedHandler<MultiPaxos, S>;
+
+/// Ballots are ordered by their round and then by the number of the proposing Node, so two
+/// Nodes never propose with the same ballot.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Ballot {
+    pub round: u64,
+    pub node_number: usize,
+}
+
+/// Value accepted for a slot of the log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptedValue {
+    pub slot: u64,
+    pub ballot: Ballot,
+    pub value: serde_json::Value,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaxosRole {
+    Acceptor,
+    /// Waiting for the promises of a majority for its ballot (phase 1).
+    Candidate,
+    /// Proposes values with its ballot without running phase 1 again (phase 2 only).
+    Leader,
+}
+
+/// Multi-Paxos over all Server Nodes with a stable leader.
+/// Every Node is an acceptor. A Node that has not heard from the leader for the leader
+/// timeout prepares a higher ballot (phase 1) and, once a majority has promised it, becomes the
+/// leader and proposes every value with that ballot (phase 2) until a higher ballot is seen.
+/// The chosen values are sent to the followers with the heartbeats.
+/// NOTE: The log is never compacted.
+/// Requires the PacketHandler to have a tick interval.
+pub struct MultiPaxos {
+    pub role: PaxosRole,
+    /// The highest ballot this Node has promised (or prepared itself).
+    pub ballot: Ballot,
+    /// The Node that proposed the promised ballot.
+    pub leader: Option<NodeId>,
+    /// The values accepted by this Node by their slot (the first slot is 1).
+    pub accepted: BTreeMap<u64, AcceptedValue>,
+    /// The chosen values by their slot.
+    pub chosen: BTreeMap<u64, serde_json::Value>,
+    /// All slots up to the commit index have been chosen.
+    pub commit_index: u64,
+    /// Slot of the last value that has been taken (see Consensus::take_committed).
+    pub last_applied: u64,
+    /// Nodes that promised the ballot and the values they had accepted (candidate only).
+    pub promises: HashMap<NodeId, Vec<AcceptedValue>>,
+    /// Nodes that accepted the proposal for every slot that has not been chosen yet (leader
+    /// only).
+    pub accepts: BTreeMap<u64, HashSet<NodeId>>,
+    /// The commit index reported by every follower (leader only).
+    pub follower_commit: HashMap<NodeId, u64>,
+    pub next_slot: u64,
+    /// The leader timeout is chosen randomly between leader_timeout and twice the
+    /// leader_timeout, so the Nodes rarely prepare at the same time.
+    pub leader_timeout: Duration,
+    pub leader_deadline: Instant,
+    pub heartbeat_interval: Duration,
+    pub last_heartbeat: Instant,
+}
+
+impl MultiPaxos {
+    pub fn new(leader_timeout: Duration, heartbeat_interval: Duration) -> Self {
+        let mut paxos = Self {
+            role: PaxosRole::Acceptor,
+            ballot: Ballot::default(),
+            leader: None,
+            accepted: BTreeMap::new(),
+            chosen: BTreeMap::new(),
+            commit_index: 0,
+            last_applied: 0,
+            promises: HashMap::new(),
+            accepts: BTreeMap::new(),
+            follower_commit: HashMap::new(),
+            next_slot: 1,
+            leader_timeout,
+            leader_deadline: Instant::now(),
+            heartbeat_interval,
+            last_heartbeat: Instant::now(),
+        };
+        paxos.reset_leader_deadline();
+        paxos
+    }
+    pub fn is_leader(&self) -> bool {
+        self.role == PaxosRole::Leader
+    }
+    fn peers<'a>(&self, state: &'a NodeInfo) -> impl Iterator<Item = &'a NodeId> {
+        let node_id = state.node_id;
+        state.server_nodes.iter().filter(move |n| **n != node_id)
+    }
+    fn majority(state: &NodeInfo) -> usize {
+        state.server_nodes.len() / 2 + 1
+    }
+    fn reset_leader_deadline(&mut self) {
+        let timeout = match self.leader_timeout.is_zero() {
+            true => Duration::ZERO,
+            false => rand::thread_rng().gen_range(self.leader_timeout..self.leader_timeout * 2),
+        };
+        self.leader_deadline = Instant::now() + timeout;
+    }
+    fn send(dest: &NodeId, payload: Payload) -> MessageResponse {
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest: *dest,
+            in_reply_to: Option::None,
+            payload,
+        }
+    }
+    /// Promises the ballot if it is at least as high as the promised one.
+    /// Returns false if a higher ballot has already been promised.
+    fn promise(&mut self, ballot: Ballot, proposer: &NodeId) -> bool {
+        if ballot < self.ballot {
+            return false;
+        }
+        if ballot > self.ballot {
+            self.step_down();
+        }
+        self.ballot = ballot;
+        self.leader = Some(*proposer);
+        self.reset_leader_deadline();
+        true
+    }
+    fn step_down(&mut self) {
+        self.role = PaxosRole::Acceptor;
+        self.promises.clear();
+        self.accepts.clear();
+        self.follower_commit.clear();
+    }
+    fn choose(&mut self, slot: u64, value: serde_json::Value) {
+        self.accepts.remove(&slot);
+        self.chosen.insert(slot, value);
+        while self.chosen.contains_key(&(self.commit_index + 1)) {
+            self.commit_index += 1;
+        }
+    }
+    fn prepare(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.step_down();
+        self.role = PaxosRole::Candidate;
+        self.ballot = Ballot {
+            round: self.ballot.round + 1,
+            node_number: state.node_number,
+        };
+        self.leader = None;
+        self.reset_leader_deadline();
+
+        let own = self.accepted_after(self.commit_index);
+        self.promises.insert(state.node_id, own);
+        if self.promises.len() >= Self::majority(state) {
+            return self.become_leader(state);
+        }
+        self.peers(state)
+            .map(|n| {
+                Self::send(
+                    n,
+                    Payload::Prepare {
+                        ballot: self.ballot,
+                        commit_index: self.commit_index,
+                    },
+                )
+            })
+            .collect()
+    }
+    fn accepted_after(&self, slot: u64) -> Vec<AcceptedValue> {
+        self.accepted
+            .range(slot + 1..)
+            .map(|(_, a)| a.clone())
+            .collect()
+    }
+    /// Proposes the value with the highest ballot any promising Node has accepted for every
+    /// slot that has not been chosen and an empty value for the slots without one.
+    fn become_leader(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.role = PaxosRole::Leader;
+        self.leader = Some(state.node_id);
+        self.follower_commit = self.peers(state).map(|n| (*n, 0)).collect();
+
+        let mut values: BTreeMap<u64, AcceptedValue> = BTreeMap::new();
+        for accepted in std::mem::take(&mut self.promises).into_values().flatten() {
+            match values.get(&accepted.slot) {
+                Some(a) if a.ballot >= accepted.ballot => {}
+                _ => {
+                    values.insert(accepted.slot, accepted);
+                }
+            }
+        }
+        let last_slot = values
+            .keys()
+            .chain(self.chosen.keys())
+            .max()
+            .cloned()
+            .unwrap_or(0)
+            .max(self.commit_index);
+        self.next_slot = last_slot + 1;
+
+        let mut responses = Vec::new();
+        for slot in self.commit_index + 1..=last_slot {
+            if self.chosen.contains_key(&slot) {
+                continue;
+            }
+            let value = match values.remove(&slot) {
+                Some(accepted) => accepted.value,
+                None => serde_json::Value::Null,
+            };
+            responses.extend(self.accept(slot, value, state));
+        }
+        responses.extend(self.heartbeat(state));
+        responses
+    }
+    /// Accepts the value for the slot with the own ballot and sends it to the acceptors.
+    fn accept(
+        &mut self,
+        slot: u64,
+        value: serde_json::Value,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        self.accepted.insert(
+            slot,
+            AcceptedValue {
+                slot,
+                ballot: self.ballot,
+                value: value.clone(),
+            },
+        );
+        self.accepts.insert(slot, HashSet::from([state.node_id]));
+        // NOTE: A single Node cluster chooses without any replies.
+        if Self::majority(state) == 1 {
+            self.choose(slot, value);
+            return Vec::with_capacity(0);
+        }
+        self.peers(state)
+            .map(|n| {
+                Self::send(
+                    n,
+                    Payload::Accept {
+                        ballot: self.ballot,
+                        slot,
+                        value: value.clone(),
+                    },
+                )
+            })
+            .collect()
+    }
+    /// Sends every follower the chosen values it is missing (or an empty heartbeat) and
+    /// resends the proposals it has not accepted yet.
+    fn heartbeat(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.last_heartbeat = Instant::now();
+        let mut responses = Vec::new();
+        for n in self.peers(state) {
+            let commit_index = self.follower_commit.get(n).cloned().unwrap_or(0);
+            responses.push(Self::send(
+                n,
+                Payload::Decide {
+                    ballot: self.ballot,
+                    first_slot: commit_index + 1,
+                    values: (commit_index + 1..)
+                        .map_while(|slot| self.chosen.get(&slot).cloned())
+                        .collect(),
+                },
+            ));
+            for (slot, acceptors) in self.accepts.iter() {
+                if !acceptors.contains(n) {
+                    responses.push(Self::send(
+                        n,
+                        Payload::Accept {
+                            ballot: self.ballot,
+                            slot: *slot,
+                            value: self.accepted[slot].value.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+        responses
+    }
+}
+
+impl Consensus for MultiPaxos {
+    fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+    fn propose(
+        &mut self,
+        command: serde_json::Value,
+        state: &NodeInfo,
+    ) -> Option<(u64, Vec<MessageResponse>)> {
+        if !self.is_leader() {
+            return None;
+        }
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        Some((slot, self.accept(slot, command, state)))
+    }
+    fn take_committed(&mut self) -> Vec<Committed> {
+        let committed = (self.last_applied + 1..=self.commit_index)
+            .map(|index| Committed::Entry {
+                index,
+                command: self.chosen[&index].clone(),
+            })
+            .collect();
+        self.last_applied = self.commit_index;
+        committed
+    }
+}
+
+impl MessageHandler for MultiPaxos {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::Prepare {
+                                ballot,
+                                commit_index,
+                            },
+                        ..
+                    },
+                ..
+            } => match self.promise(*ballot, src) {
+                true => Collection::One(Self::send(
+                    src,
+                    Payload::Promise {
+                        ballot: *ballot,
+                        accepted: self.accepted_after(*commit_index),
+                    },
+                )),
+                false => Collection::One(Self::send(
+                    src,
+                    Payload::Rejected {
+                        ballot: self.ballot,
+                    },
+                )),
+            },
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::Promise { ballot, accepted },
+                        ..
+                    },
+                ..
+            } => {
+                if self.role != PaxosRole::Candidate || *ballot != self.ballot {
+                    return Collection::None;
+                }
+                self.promises.insert(*src, accepted.clone());
+                match self.promises.len() >= Self::majority(state) {
+                    true => self.become_leader(state).into(),
+                    false => Collection::None,
+                }
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::Accept {
+                                ballot,
+                                slot,
+                                value,
+                            },
+                        ..
+                    },
+                ..
+            } => match self.promise(*ballot, src) {
+                true => {
+                    self.accepted.insert(
+                        *slot,
+                        AcceptedValue {
+                            slot: *slot,
+                            ballot: *ballot,
+                            value: value.clone(),
+                        },
+                    );
+                    Collection::One(Self::send(
+                        src,
+                        Payload::Accepted {
+                            ballot: *ballot,
+                            slot: *slot,
+                        },
+                    ))
+                }
+                false => Collection::One(Self::send(
+                    src,
+                    Payload::Rejected {
+                        ballot: self.ballot,
+                    },
+                )),
+            },
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::Accepted { ballot, slot },
+                        ..
+                    },
+                ..
+            } => {
+                if !self.is_leader() || *ballot != self.ballot {
+                    return Collection::None;
+                }
+                let Some(acceptors) = self.accepts.get_mut(slot) else {
+                    return Collection::None;
+                };
+                acceptors.insert(*src);
+                if acceptors.len() >= Self::majority(state) {
+                    let value = self.accepted[slot].value.clone();
+                    self.choose(*slot, value);
+                }
+                Collection::None
+            }
+            // NOTE: Chosen values never change, so they are stored even if they were sent with
+            // an old ballot.
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::Decide {
+                                ballot,
+                                first_slot,
+                                values,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                for (i, value) in values.iter().enumerate() {
+                    self.chosen.insert(first_slot + i as u64, value.clone());
+                }
+                while self.chosen.contains_key(&(self.commit_index + 1)) {
+                    self.commit_index += 1;
+                }
+                match self.promise(*ballot, src) {
+                    true => Collection::One(Self::send(
+                        src,
+                        Payload::DecideOk {
+                            ballot: *ballot,
+                            commit_index: self.commit_index,
+                        },
+                    )),
+                    false => Collection::None,
+                }
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::DecideOk {
+                                ballot,
+                                commit_index,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                if self.is_leader() && *ballot == self.ballot {
+                    self.follower_commit.insert(*src, *commit_index);
+                }
+                Collection::None
+            }
+            Packet {
+                body:
+                    Message {
+                        payload: Payload::Rejected { ballot },
+                        ..
+                    },
+                ..
+            } => {
+                if *ballot > self.ballot {
+                    self.step_down();
+                    self.ballot = *ballot;
+                    self.leader = None;
+                    self.reset_leader_deadline();
+                }
+                Collection::None
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        match self.role {
+            PaxosRole::Leader if self.last_heartbeat.elapsed() >= self.heartbeat_interval => {
+                self.heartbeat(state).into()
+            }
+            PaxosRole::Leader => Collection::None,
+            PaxosRole::Acceptor | PaxosRole::Candidate
+                if Instant::now() >= self.leader_deadline =>
+            {
+                self.prepare(state).into()
+            }
+            PaxosRole::Acceptor | PaxosRole::Candidate => Collection::None,
+        }
+    }
+}
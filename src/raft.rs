@@ -0,0 +1,7 @@
+use crate::{raft::consensus::Raft, replication::handler::ReplicatedHandler};
+
+pub mod consensus;
+pub mod log;
+
+/// Makes a StateMachine linearizable by replicating its requests with Raft.
+pub type RaftHandler<S> = ReplicatedHandler<Raft, S>;
@@ -0,0 +1,489 @@
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    raft::log::{LogEntry, RaftLog},
+    replication::{Committed, Consensus},
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_id::NodeId, node_info::NodeInfo, packet::Packet,
+        payload::Payload,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RaftRole {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Raft consensus over all Server Nodes.
+/// Commands are proposed to the leader and can be taken from the Raft once they have been
+/// committed, in the same order on every Node.
+/// Requires the PacketHandler to have a tick interval.
+pub struct Raft {
+    pub role: RaftRole,
+    pub term: u64,
+    pub voted_for: Option<NodeId>,
+    /// The leader of the current term, if it is known.
+    pub leader: Option<NodeId>,
+    pub log: RaftLog,
+    pub commit_index: u64,
+    /// Index of the last entry that has been taken (see Consensus::take_committed).
+    pub last_applied: u64,
+    /// Index of the next entry to send to every follower (leader only).
+    pub next_index: HashMap<NodeId, u64>,
+    /// Index of the last entry known to be replicated on every follower (leader only).
+    pub match_index: HashMap<NodeId, u64>,
+    /// Nodes that voted for this Node in the current term (candidate only).
+    pub votes: HashSet<NodeId>,
+    /// The election timeout is chosen randomly between election_timeout and twice the
+    /// election_timeout, so the Nodes rarely start elections at the same time.
+    pub election_timeout: Duration,
+    pub election_deadline: Instant,
+    pub heartbeat_interval: Duration,
+    pub last_heartbeat: Instant,
+}
+
+impl Raft {
+    pub fn new(election_timeout: Duration, heartbeat_interval: Duration) -> Self {
+        let mut raft = Self {
+            role: RaftRole::Follower,
+            term: 0,
+            voted_for: None,
+            leader: None,
+            log: RaftLog::default(),
+            commit_index: 0,
+            last_applied: 0,
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            votes: HashSet::new(),
+            election_timeout,
+            election_deadline: Instant::now(),
+            heartbeat_interval,
+            last_heartbeat: Instant::now(),
+        };
+        raft.reset_election_deadline();
+        raft
+    }
+    pub fn is_leader(&self) -> bool {
+        self.role == RaftRole::Leader
+    }
+    fn peers<'a>(&self, state: &'a NodeInfo) -> impl Iterator<Item = &'a NodeId> {
+        let node_id = state.node_id;
+        state.server_nodes.iter().filter(move |n| **n != node_id)
+    }
+    fn majority(state: &NodeInfo) -> usize {
+        state.server_nodes.len() / 2 + 1
+    }
+    fn reset_election_deadline(&mut self) {
+        let timeout = match self.election_timeout.is_zero() {
+            true => Duration::ZERO,
+            false => rand::thread_rng().gen_range(self.election_timeout..self.election_timeout * 2),
+        };
+        self.election_deadline = Instant::now() + timeout;
+    }
+    /// Steps down to follower if the term is newer than the current one.
+    fn observe_term(&mut self, term: u64) {
+        if term > self.term {
+            self.term = term;
+            self.role = RaftRole::Follower;
+            self.voted_for = None;
+            self.leader = None;
+        }
+    }
+    /// Follows the leader of the term (after checking the term with observe_term).
+    fn follow(&mut self, leader: &NodeId) {
+        self.role = RaftRole::Follower;
+        self.leader = Some(*leader);
+        self.reset_election_deadline();
+    }
+    fn start_election(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.term += 1;
+        self.role = RaftRole::Candidate;
+        self.voted_for = Some(state.node_id);
+        self.leader = None;
+        self.votes = HashSet::from([state.node_id]);
+        self.reset_election_deadline();
+
+        if self.votes.len() >= Self::majority(state) {
+            return self.become_leader(state);
+        }
+        self.peers(state)
+            .map(|n| MessageResponse::NoAck {
+                src: Option::None,
+                dest: *n,
+                in_reply_to: Option::None,
+                payload: Payload::RequestVote {
+                    term: self.term,
+                    last_log_index: self.log.last_index(),
+                    last_log_term: self.log.last_term(),
+                },
+            })
+            .collect()
+    }
+    fn become_leader(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.role = RaftRole::Leader;
+        self.leader = Some(state.node_id);
+        let next_index = self.log.last_index() + 1;
+        self.next_index = self.peers(state).map(|n| (*n, next_index)).collect();
+        self.match_index = self.peers(state).map(|n| (*n, 0)).collect();
+
+        // NOTE: Entries of previous terms are only committed together with an entry of the
+        // current term, so every leader appends an empty entry.
+        self.propose(serde_json::Value::Null, state)
+            .map(|(_, responses)| responses)
+            .unwrap_or_default()
+    }
+    /// Sends every follower the entries it is missing (or an empty heartbeat) and the
+    /// snapshot instead if the entries have already been compacted.
+    fn replicate(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        self.last_heartbeat = Instant::now();
+        self.peers(state)
+            .map(|n| {
+                let next_index = self.next_index.get(n).cloned().unwrap_or(1).max(1);
+                let prev_log_index = next_index - 1;
+                let payload = match self.log.term_at(prev_log_index) {
+                    Some(prev_log_term) => Payload::AppendEntries {
+                        term: self.term,
+                        prev_log_index,
+                        prev_log_term,
+                        entries: self.log.entries_from(next_index).to_vec(),
+                        leader_commit: self.commit_index,
+                    },
+                    None => Payload::InstallSnapshot {
+                        term: self.term,
+                        last_included_index: self.log.snapshot_index,
+                        last_included_term: self.log.snapshot_term,
+                        snapshot: self.log.snapshot.clone(),
+                    },
+                };
+                MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *n,
+                    in_reply_to: Option::None,
+                    payload,
+                }
+            })
+            .collect()
+    }
+    /// Commits the newest entry of the current term that is stored on a majority of Nodes.
+    fn advance_commit_index(&mut self, state: &NodeInfo) {
+        for index in (self.commit_index + 1..=self.log.last_index()).rev() {
+            if self.log.term_at(index) != Some(self.term) {
+                break;
+            }
+            let replicas = 1 + self.match_index.values().filter(|m| **m >= index).count();
+            if replicas >= Self::majority(state) {
+                self.commit_index = index;
+                break;
+            }
+        }
+    }
+    fn append_entries_ok(
+        &self,
+        leader: &NodeId,
+        success: bool,
+        match_index: u64,
+    ) -> MessageResponse {
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest: *leader,
+            in_reply_to: Option::None,
+            payload: Payload::AppendEntriesOk {
+                term: self.term,
+                success,
+                match_index,
+            },
+        }
+    }
+    fn handle_request_vote(
+        &mut self,
+        candidate: &NodeId,
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    ) -> MessageResponse {
+        self.observe_term(term);
+        let up_to_date =
+            (last_log_term, last_log_index) >= (self.log.last_term(), self.log.last_index());
+        let vote_granted =
+            term == self.term && up_to_date && self.voted_for.is_none_or(|v| v == *candidate);
+        if vote_granted {
+            self.voted_for = Some(*candidate);
+            self.reset_election_deadline();
+        }
+        MessageResponse::NoAck {
+            src: Option::None,
+            dest: *candidate,
+            in_reply_to: Option::None,
+            payload: Payload::RequestVoteOk {
+                term: self.term,
+                vote_granted,
+            },
+        }
+    }
+    fn handle_append_entries(
+        &mut self,
+        leader: &NodeId,
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: &[LogEntry],
+        leader_commit: u64,
+    ) -> MessageResponse {
+        self.observe_term(term);
+        if term != self.term {
+            return self.append_entries_ok(leader, false, self.log.last_index());
+        }
+        self.follow(leader);
+
+        // NOTE: Entries up to the snapshot are committed, so they match the entries of the
+        // leader and are skipped.
+        let skip = self.log.snapshot_index.saturating_sub(prev_log_index);
+        let (prev_log_index, prev_log_term, entries) = match skip {
+            0 => (prev_log_index, prev_log_term, entries),
+            skip if skip as usize <= entries.len() => (
+                self.log.snapshot_index,
+                self.log.snapshot_term,
+                &entries[skip as usize..],
+            ),
+            _ => (
+                self.log.snapshot_index,
+                self.log.snapshot_term,
+                &entries[..0],
+            ),
+        };
+        if self.log.term_at(prev_log_index) != Some(prev_log_term) {
+            return self.append_entries_ok(leader, false, self.log.last_index());
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            let index = prev_log_index + 1 + i as u64;
+            match self.log.term_at(index) {
+                Some(term) if term == entry.term => {}
+                // NOTE: Conflicting entries have never been committed, so they (and all
+                // entries after them) are replaced by the entries of the leader.
+                Some(_) => {
+                    self.log.truncate(index);
+                    self.log.push(entry.clone());
+                }
+                None => self.log.push(entry.clone()),
+            }
+        }
+        let match_index = prev_log_index + entries.len() as u64;
+        self.commit_index = self.commit_index.max(leader_commit.min(match_index));
+        self.append_entries_ok(leader, true, match_index)
+    }
+    fn handle_install_snapshot(
+        &mut self,
+        leader: &NodeId,
+        term: u64,
+        last_included_index: u64,
+        last_included_term: u64,
+        snapshot: &serde_json::Value,
+    ) -> MessageResponse {
+        self.observe_term(term);
+        if term != self.term {
+            return self.append_entries_ok(leader, false, self.log.last_index());
+        }
+        self.follow(leader);
+
+        // NOTE: Snapshots only contain committed entries, so a snapshot up to the commit index
+        // contains nothing this Node does not have already.
+        if last_included_index > self.commit_index {
+            self.log
+                .install(last_included_index, last_included_term, snapshot.clone());
+            self.commit_index = last_included_index;
+        }
+        self.append_entries_ok(leader, true, last_included_index)
+    }
+}
+
+impl Consensus for Raft {
+    fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+    fn propose(
+        &mut self,
+        command: serde_json::Value,
+        state: &NodeInfo,
+    ) -> Option<(u64, Vec<MessageResponse>)> {
+        if !self.is_leader() {
+            return None;
+        }
+        self.log.push(LogEntry {
+            term: self.term,
+            command,
+        });
+        // NOTE: A single Node cluster commits without any replies.
+        self.advance_commit_index(state);
+        Some((self.log.last_index(), self.replicate(state)))
+    }
+    fn take_committed(&mut self) -> Vec<Committed> {
+        let mut committed = Vec::new();
+        if self.last_applied < self.log.snapshot_index {
+            committed.push(Committed::Snapshot {
+                index: self.log.snapshot_index,
+                snapshot: self.log.snapshot.clone(),
+            });
+            self.last_applied = self.log.snapshot_index;
+        }
+        committed.extend(
+            (self.last_applied + 1..=self.commit_index).filter_map(|index| {
+                self.log.get(index).map(|entry| Committed::Entry {
+                    index,
+                    command: entry.command.clone(),
+                })
+            }),
+        );
+        self.last_applied = self.last_applied.max(self.commit_index);
+        committed
+    }
+    fn log_len(&self) -> usize {
+        self.log.entries.len()
+    }
+    fn compact(&mut self, snapshot: serde_json::Value) {
+        self.log.compact(self.last_applied, snapshot);
+    }
+}
+
+impl MessageHandler for Raft {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::RequestVote {
+                                term,
+                                last_log_index,
+                                last_log_term,
+                            },
+                        ..
+                    },
+                ..
+            } => Collection::One(self.handle_request_vote(
+                src,
+                *term,
+                *last_log_index,
+                *last_log_term,
+            )),
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::RequestVoteOk { term, vote_granted },
+                        ..
+                    },
+                ..
+            } => {
+                self.observe_term(*term);
+                if self.role != RaftRole::Candidate || *term != self.term || !vote_granted {
+                    return Collection::None;
+                }
+                self.votes.insert(*src);
+                match self.votes.len() >= Self::majority(state) {
+                    true => self.become_leader(state).into(),
+                    false => Collection::None,
+                }
+            }
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::AppendEntries {
+                                term,
+                                prev_log_index,
+                                prev_log_term,
+                                entries,
+                                leader_commit,
+                            },
+                        ..
+                    },
+                ..
+            } => Collection::One(self.handle_append_entries(
+                src,
+                *term,
+                *prev_log_index,
+                *prev_log_term,
+                entries,
+                *leader_commit,
+            )),
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::InstallSnapshot {
+                                term,
+                                last_included_index,
+                                last_included_term,
+                                snapshot,
+                            },
+                        ..
+                    },
+                ..
+            } => Collection::One(self.handle_install_snapshot(
+                src,
+                *term,
+                *last_included_index,
+                *last_included_term,
+                snapshot,
+            )),
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload:
+                            Payload::AppendEntriesOk {
+                                term,
+                                success,
+                                match_index,
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                self.observe_term(*term);
+                if !self.is_leader() || *term != self.term {
+                    return Collection::None;
+                }
+                let next_index = self.next_index.entry(*src).or_insert(1);
+                match success {
+                    true => {
+                        let own_match = self.match_index.entry(*src).or_default();
+                        *own_match = (*own_match).max(*match_index);
+                        *next_index = (*next_index).max(match_index + 1);
+                        self.advance_commit_index(state);
+                    }
+                    // NOTE: The follower is missing entries or has conflicting ones, so the
+                    // next AppendEntries starts at most after its last entry.
+                    false => *next_index = (*next_index - 1).min(match_index + 1).max(1),
+                }
+                Collection::None
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        match self.role {
+            RaftRole::Leader if self.last_heartbeat.elapsed() >= self.heartbeat_interval => {
+                self.replicate(state).into()
+            }
+            RaftRole::Leader => Collection::None,
+            RaftRole::Follower | RaftRole::Candidate
+                if Instant::now() >= self.election_deadline =>
+            {
+                self.start_election(state).into()
+            }
+            RaftRole::Follower | RaftRole::Candidate => Collection::None,
+        }
+    }
+}
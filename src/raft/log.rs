@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub term: u64,
+    /// The command for the state machine, Null for the entry every leader appends when it is
+    /// elected.
+    pub command: serde_json::Value,
+}
+
+/// Raft log whose prefix can be replaced by a snapshot of the state machine.
+/// The first entry has index 1, entries up to snapshot_index are only part of the snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RaftLog {
+    pub entries: Vec<LogEntry>,
+    /// Index and term of the last entry contained in the snapshot, 0 without a snapshot.
+    pub snapshot_index: u64,
+    pub snapshot_term: u64,
+    pub snapshot: serde_json::Value,
+}
+
+impl RaftLog {
+    pub fn last_index(&self) -> u64 {
+        self.snapshot_index + self.entries.len() as u64
+    }
+    pub fn last_term(&self) -> u64 {
+        self.entries.last().map_or(self.snapshot_term, |e| e.term)
+    }
+    /// Returns the term of the entry at the index, None if the entry does not exist or has
+    /// been compacted.
+    pub fn term_at(&self, index: u64) -> Option<u64> {
+        match index.cmp(&self.snapshot_index) {
+            std::cmp::Ordering::Less => None,
+            std::cmp::Ordering::Equal => Some(self.snapshot_term),
+            std::cmp::Ordering::Greater => self.get(index).map(|e| e.term),
+        }
+    }
+    pub fn get(&self, index: u64) -> Option<&LogEntry> {
+        match index > self.snapshot_index {
+            true => self.entries.get((index - self.snapshot_index - 1) as usize),
+            false => None,
+        }
+    }
+    /// Returns the entries starting at the index, which has to be after the snapshot.
+    pub fn entries_from(&self, index: u64) -> &[LogEntry] {
+        let start = (index.max(self.snapshot_index + 1) - self.snapshot_index - 1) as usize;
+        &self.entries[start.min(self.entries.len())..]
+    }
+    pub fn push(&mut self, entry: LogEntry) {
+        self.entries.push(entry);
+    }
+    /// Removes the entry at the index and all entries after it.
+    pub fn truncate(&mut self, index: u64) {
+        if index > self.snapshot_index {
+            self.entries
+                .truncate((index - self.snapshot_index - 1) as usize);
+        }
+    }
+    /// Replaces the entries up to the index with the snapshot of the state after applying
+    /// them.
+    pub fn compact(&mut self, index: u64, snapshot: serde_json::Value) {
+        let Some(term) = self.term_at(index) else {
+            return;
+        };
+        self.entries.drain(..(index - self.snapshot_index) as usize);
+        self.snapshot_index = index;
+        self.snapshot_term = term;
+        self.snapshot = snapshot;
+    }
+    /// Installs a snapshot received from the leader.
+    /// Entries after the snapshot are kept if the log contains the last entry of the snapshot,
+    /// otherwise the whole log is replaced.
+    pub fn install(&mut self, index: u64, term: u64, snapshot: serde_json::Value) {
+        match self.term_at(index) {
+            Some(t) if t == term => {
+                self.entries.drain(..(index - self.snapshot_index) as usize);
+            }
+            _ => self.entries.clear(),
+        }
+        self.snapshot_index = index;
+        self.snapshot_term = term;
+        self.snapshot = snapshot;
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::types::{
+    message_handler::MessageHandler, message_response::MessageResponse, node_id::NodeId,
+    node_info::NodeInfo, payload::Payload,
+};
+
+pub mod handler;
+
+/// Deterministic service that is replicated by applying the same requests in the same order
+/// on every Node (see ReplicatedHandler).
+/// The state machine is serialized as the snapshot when the log is compacted.
+pub trait StateMachine: Serialize + DeserializeOwned {
+    /// Returns true if the Payload is a Client request that is applied through the log.
+    fn is_request(&self, payload: &Payload) -> bool;
+    /// Applies a committed request and returns the reply to the Client.
+    fn apply(&mut self, request: &Payload) -> Payload;
+}
+
+/// Client request stored in the log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientRequest {
+    pub client: NodeId,
+    pub msg_id: Option<usize>,
+    pub payload: Payload,
+}
+
+/// What has to be applied to the state machine next (see Consensus::take_committed).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Committed {
+    /// The command at the index of the log, Null for the empty entries a new leader proposes.
+    Entry {
+        index: u64,
+        command: serde_json::Value,
+    },
+    /// Replaces the state machine, because the entries up to the index have been compacted
+    /// into a snapshot by the leader.
+    Snapshot {
+        index: u64,
+        snapshot: serde_json::Value,
+    },
+}
+
+/// Protocol that agrees on the order of the commands in a log (e.g. Raft or Multi-Paxos).
+/// The consensus Payloads are handled through MessageHandler.
+pub trait Consensus: MessageHandler {
+    /// Returns the Node that accepts proposals, if it is known.
+    fn leader(&self) -> Option<NodeId>;
+    /// Appends the command to the log if this Node is the leader.
+    /// Returns the index of the new entry and the Messages to replicate it.
+    fn propose(
+        &mut self,
+        command: serde_json::Value,
+        state: &NodeInfo,
+    ) -> Option<(u64, Vec<MessageResponse>)>;
+    /// Returns the committed entries that have not been taken yet, in the order of the log.
+    fn take_committed(&mut self) -> Vec<Committed>;
+    /// Returns the number of entries that could be compacted into a snapshot.
+    fn log_len(&self) -> usize {
+        0
+    }
+    /// Replaces the taken entries with the snapshot of the state machine after applying
+    /// them. Does nothing if the protocol does not support snapshots.
+    fn compact(&mut self, _snapshot: serde_json::Value) {}
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    replication::{ClientRequest, Committed, Consensus, StateMachine},
+    types::{
+        collection::Collection, message_handler::MessageHandler, message_response::MessageResponse,
+        node_id::NodeId, node_info::NodeInfo, packet::Packet, payload::Payload,
+    },
+};
+
+/// Everything the applied entries have changed, which is stored in the snapshot.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<S> {
+    state_machine: S,
+    last_requests: HashMap<NodeId, (usize, Payload)>,
+}
+
+/// Makes a StateMachine linearizable by replicating its requests with a Consensus protocol.
+/// Every request (including reads) is appended to the log and applied once it has been
+/// committed, so all Nodes apply the requests in the same order. Followers forward requests to
+/// the leader, which replies once the request has been applied (see PacketHandler for how the
+/// reply is relayed through the follower).
+/// Requires the PacketHandler to have a tick interval.
+pub struct ReplicatedHandler<C: Consensus, S: StateMachine> {
+    pub consensus: C,
+    pub state_machine: S,
+    /// The msg_id of the last applied request of every Client and its response, so retried
+    /// requests are not applied twice.
+    pub last_requests: HashMap<NodeId, (usize, Payload)>,
+    /// The Client and msg_id of the requests proposed by this Node by their index, which are
+    /// answered once they have been applied (unless another request ended up at the index).
+    pub pending: HashMap<u64, (NodeId, Option<usize>)>,
+    /// Number of log entries after which the applied entries are compacted into a snapshot,
+    /// the log is never compacted if None.
+    pub snapshot_threshold: Option<usize>,
+}
+
+impl<C: Consensus, S: StateMachine> ReplicatedHandler<C, S> {
+    pub fn new(consensus: C, state_machine: S) -> Self {
+        Self {
+            consensus,
+            state_machine,
+            last_requests: HashMap::new(),
+            pending: HashMap::new(),
+            snapshot_threshold: None,
+        }
+    }
+    pub fn set_snapshot_threshold(&mut self, snapshot_threshold: usize) {
+        self.snapshot_threshold = Some(snapshot_threshold);
+    }
+    fn cached_response(&self, client: &NodeId, msg_id: Option<usize>) -> Option<&Payload> {
+        match (self.last_requests.get(client), msg_id) {
+            (Some((last, response)), Some(msg_id)) if *last == msg_id => Some(response),
+            _ => None,
+        }
+    }
+    fn handle_request(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        let Packet { src, body, .. } = packet;
+        if let Some(response) = self.cached_response(src, body.msg_id) {
+            return Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: body.msg_id,
+                payload: response.clone(),
+            });
+        }
+
+        let request = ClientRequest {
+            client: *src,
+            msg_id: body.msg_id,
+            payload: body.payload.clone(),
+        };
+        let command = serde_json::to_value(request).unwrap_or_default();
+        if let Some((index, responses)) = self.consensus.propose(command, state) {
+            self.pending.insert(index, (*src, body.msg_id));
+            return Collection::from(responses) + self.apply_committed();
+        }
+
+        match self.consensus.leader() {
+            Some(leader) => Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: leader,
+                in_reply_to: Option::None,
+                payload: Payload::Forward {
+                    packet: Box::new(Packet {
+                        src: *src,
+                        dest: leader,
+                        body: body.clone(),
+                    }),
+                },
+            }),
+            None => Collection::One(MessageResponse::NoAck {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: body.msg_id,
+                payload: Payload::Error {
+                    code: 11,
+                    text: "No leader is known".to_string(),
+                },
+            }),
+        }
+    }
+    /// Applies the committed entries, answers the ones proposed by this Node and compacts the
+    /// log if it has grown past the snapshot threshold.
+    fn apply_committed(&mut self) -> Collection<MessageResponse> {
+        let mut responses = Vec::new();
+        for committed in self.consensus.take_committed() {
+            let (index, command) = match committed {
+                Committed::Entry { index, command } => (index, command),
+                Committed::Snapshot { snapshot, .. } => {
+                    if let Ok(snapshot) = serde_json::from_value::<Snapshot<S>>(snapshot) {
+                        self.state_machine = snapshot.state_machine;
+                        self.last_requests = snapshot.last_requests;
+                    }
+                    continue;
+                }
+            };
+            let Ok(request) = serde_json::from_value::<ClientRequest>(command) else {
+                continue;
+            };
+            let response = match self.cached_response(&request.client, request.msg_id) {
+                Some(response) => response.clone(),
+                None => self.state_machine.apply(&request.payload),
+            };
+            if let Some(msg_id) = request.msg_id {
+                self.last_requests
+                    .insert(request.client, (msg_id, response.clone()));
+            }
+            if self.pending.remove(&index) == Some((request.client, request.msg_id)) {
+                responses.push(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: request.client,
+                    in_reply_to: request.msg_id,
+                    payload: response,
+                });
+            }
+        }
+
+        if self
+            .snapshot_threshold
+            .is_some_and(|t| self.consensus.log_len() > t)
+        {
+            let snapshot = Snapshot {
+                state_machine: &self.state_machine,
+                last_requests: self.last_requests.clone(),
+            };
+            self.consensus
+                .compact(serde_json::to_value(snapshot).unwrap_or_default());
+        }
+        responses.into()
+    }
+}
+
+impl<C: Consensus, S: StateMachine> MessageHandler for ReplicatedHandler<C, S> {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        match self.state_machine.is_request(&packet.body.payload) {
+            true => self.handle_request(packet, state),
+            false => self.consensus.handle_message(packet, state) + self.apply_committed(),
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        self.consensus.handle_tick(state) + self.apply_committed()
+    }
+}
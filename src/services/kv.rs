@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::types::{
+    message::Message, message_response::MessageResponse, node_id::NodeId, packet::Packet,
+    payload::Payload,
+};
+
+/// Request to the key-value service that has not been answered yet.
+pub struct KvRequest<T> {
+    pub waiter: T,
+    pub payload: Payload,
+    pub sent: Instant,
+}
+
+/// Reply of the key-value service and the request it answers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KvReply<T> {
+    pub waiter: T,
+    pub request: Payload,
+    pub reply: Payload,
+}
+
+/// Client of Maelstrom's key-value services ("lin-kv", "seq-kv" and "lww-kv").
+/// The waiter identifies who made the request and is returned with the reply.
+/// NOTE: Requests whose reply is overdue are sent again, so a "cas" can fail because its
+/// first attempt has already been applied.
+/// NOTE: The msg_ids of the requests are counted by the client, so every service may only be
+/// used by a single client per Node, otherwise their replies are mixed up.
+pub struct KvClient<T> {
+    pub service: NodeId,
+    pub next_msg_id: usize,
+    pub pending: HashMap<usize, KvRequest<T>>,
+    pub timeout: Duration,
+}
+
+impl<T> KvClient<T> {
+    pub fn new(service: &str, timeout: Duration) -> Self {
+        Self {
+            service: NodeId::from(service),
+            next_msg_id: 0,
+            pending: HashMap::new(),
+            timeout,
+        }
+    }
+    pub fn read(&mut self, key: serde_json::Value, waiter: T) -> MessageResponse {
+        self.send(Payload::Read { key: Some(key) }, waiter)
+    }
+    pub fn write(
+        &mut self,
+        key: serde_json::Value,
+        value: serde_json::Value,
+        waiter: T,
+    ) -> MessageResponse {
+        self.send(Payload::Write { key, value }, waiter)
+    }
+    pub fn cas(
+        &mut self,
+        key: serde_json::Value,
+        from: serde_json::Value,
+        to: serde_json::Value,
+        create_if_not_exists: bool,
+        waiter: T,
+    ) -> MessageResponse {
+        let payload = Payload::Cas {
+            key,
+            from,
+            to,
+            create_if_not_exists,
+        };
+        self.send(payload, waiter)
+    }
+    /// Returns None if the Packet is not a reply to a pending request.
+    pub fn handle_reply(&mut self, packet: &Packet) -> Option<KvReply<T>> {
+        let Packet {
+            src,
+            body:
+                Message {
+                    in_reply_to: Some(in_reply_to),
+                    payload,
+                    ..
+                },
+            ..
+        } = packet
+        else {
+            return None;
+        };
+        if *src != self.service {
+            return None;
+        }
+        let request = self.pending.remove(in_reply_to)?;
+        Some(KvReply {
+            waiter: request.waiter,
+            request: request.payload,
+            reply: payload.clone(),
+        })
+    }
+    /// Returns the overdue requests with new msg_ids.
+    pub fn handle_tick(&mut self) -> Vec<MessageResponse> {
+        let overdue: Vec<usize> = self
+            .pending
+            .iter()
+            .filter(|(_, r)| r.sent.elapsed() >= self.timeout)
+            .map(|(msg_id, _)| *msg_id)
+            .collect();
+        let mut responses = Vec::new();
+        for msg_id in overdue {
+            if let Some(request) = self.pending.remove(&msg_id) {
+                responses.push(self.send(request.payload, request.waiter));
+            }
+        }
+        responses
+    }
+    /// Sends the request (e.g. again after it has failed).
+    pub fn send(&mut self, payload: Payload, waiter: T) -> MessageResponse {
+        self.next_msg_id += 1;
+        self.pending.insert(
+            self.next_msg_id,
+            KvRequest {
+                waiter,
+                payload: payload.clone(),
+                sent: Instant::now(),
+            },
+        );
+        MessageResponse::Request {
+            dest: self.service,
+            msg_id: self.next_msg_id,
+            payload,
+        }
+    }
+}
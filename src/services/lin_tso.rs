@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use crate::types::{
+    message::Message, message_response::MessageResponse, node_id::NodeId, packet::Packet,
+    payload::Payload,
+};
+
+/// "ts" RPC that has been sent and the waiters it will answer.
+pub struct TsRequest<T> {
+    pub msg_id: usize,
+    pub waiters: Vec<T>,
+    pub sent: Instant,
+}
+
+/// Timestamps handed out by a "ts_ok" reply.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TsReply<T> {
+    /// The waiters with the "ts" of the reply and their position in the batch.
+    /// NOTE: All waiters of a batch share the "ts", so only the pair is unique.
+    pub timestamps: Vec<(T, (u64, usize))>,
+    /// The RPC for the waiters that have been queued while the reply was outstanding.
+    pub next: Option<MessageResponse>,
+}
+
+/// Client of Maelstrom's "lin-tso" timestamp oracle.
+/// At most one "ts" RPC is in flight. Waiters that request a timestamp while it is in flight
+/// are batched into the next RPC, because the timestamp of an RPC sent before the request
+/// might not be greater than every timestamp handed out before the request.
+/// The waiter identifies who requested the timestamp (e.g. the Client and msg_id of a
+/// transaction).
+/// The waiters of a batch get the same "ts" together with their position in the batch, so the
+/// timestamps are unique and ordered when compared as pairs.
+/// NOTE: The msg_ids of the RPCs are counted by the client, so there may only be a single
+/// client of "lin-tso" per Node.
+pub struct LinTsoClient<T> {
+    pub service: NodeId,
+    pub next_msg_id: usize,
+    pub in_flight: Option<TsRequest<T>>,
+    pub queued: Vec<T>,
+    /// The in-flight RPC is sent again if it has not been answered within the timeout.
+    pub timeout: Duration,
+}
+
+impl<T> LinTsoClient<T> {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            service: NodeId::from("lin-tso"),
+            next_msg_id: 0,
+            in_flight: None,
+            queued: Vec::new(),
+            timeout,
+        }
+    }
+    /// Requests a timestamp for the waiter.
+    /// Returns the "ts" RPC that has to be sent if none is in flight.
+    pub fn next_timestamp(&mut self, waiter: T) -> Option<MessageResponse> {
+        self.queued.push(waiter);
+        match self.in_flight {
+            Some(_) => None,
+            None => Some(self.send(Vec::new())),
+        }
+    }
+    /// Handles the "ts_ok" reply to the in-flight RPC.
+    /// Returns None if the Packet is not the reply.
+    pub fn handle_reply(&mut self, packet: &Packet) -> Option<TsReply<T>> {
+        let Packet {
+            src,
+            body:
+                Message {
+                    in_reply_to: Some(in_reply_to),
+                    payload: Payload::TsOk { ts },
+                    ..
+                },
+            ..
+        } = packet
+        else {
+            return None;
+        };
+        if *src != self.service || self.in_flight.as_ref()?.msg_id != *in_reply_to {
+            return None;
+        }
+        let request = self.in_flight.take()?;
+        let next = match self.queued.is_empty() {
+            true => None,
+            false => Some(self.send(Vec::new())),
+        };
+        Some(TsReply {
+            timestamps: request
+                .waiters
+                .into_iter()
+                .enumerate()
+                .map(|(i, w)| (w, (*ts, i)))
+                .collect(),
+            next,
+        })
+    }
+    /// Returns the in-flight RPC with a new msg_id if its reply is overdue.
+    pub fn handle_tick(&mut self) -> Option<MessageResponse> {
+        match self.in_flight.take() {
+            Some(request) if request.sent.elapsed() >= self.timeout => {
+                Some(self.send(request.waiters))
+            }
+            in_flight => {
+                self.in_flight = in_flight;
+                None
+            }
+        }
+    }
+    /// Sends an RPC for the waiters and the queued waiters.
+    fn send(&mut self, mut waiters: Vec<T>) -> MessageResponse {
+        waiters.append(&mut self.queued);
+        self.next_msg_id += 1;
+        self.in_flight = Some(TsRequest {
+            msg_id: self.next_msg_id,
+            waiters,
+            sent: Instant::now(),
+        });
+        MessageResponse::Request {
+            dest: self.service,
+            msg_id: self.next_msg_id,
+            payload: Payload::Ts,
+        }
+    }
+}
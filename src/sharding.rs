@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::types::{
+    collection::Collection, message_handler::MessageHandler, message_response::MessageResponse,
+    node_id::NodeId, node_info::NodeInfo, packet::Packet, payload::Payload,
+};
+
+/// Consistent-hash ring.
+/// Every member is placed on the ring at "virtual_nodes" points and a key is owned by the
+/// member with the first point at or after the hash of the key. Adding or removing a member
+/// only moves the keys next to its points.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HashRing {
+    pub members: BTreeSet<NodeId>,
+    pub points: BTreeMap<u64, NodeId>,
+}
+
+impl HashRing {
+    pub fn new(members: impl IntoIterator<Item = NodeId>, virtual_nodes: usize) -> Self {
+        let members: BTreeSet<NodeId> = members.into_iter().collect();
+        let points = members
+            .iter()
+            .flat_map(|m| {
+                (0..virtual_nodes).map(move |i| (Self::hash(format!("{}#{}", m, i).as_bytes()), *m))
+            })
+            .collect();
+        Self { members, points }
+    }
+    pub fn owner(&self, key: &serde_json::Value) -> Option<NodeId> {
+        let hash = Self::hash(key.to_string().as_bytes());
+        self.points
+            .range(hash..)
+            .chain(self.points.iter())
+            .next()
+            .map(|(_, member)| *member)
+    }
+    /// FNV-1a followed by the finalizer of SplitMix64, so the hash is the same on every Node
+    /// and similar keys are spread over the ring.
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+/// Handler whose state is partitioned by key (see ShardedHandler).
+pub trait Sharded: MessageHandler {
+    /// Returns the key of a request, or None if the Message is not partitioned (e.g. Messages
+    /// between the Nodes).
+    fn shard_key(&self, payload: &Payload) -> Option<serde_json::Value>;
+    /// Called after the members of the ring have changed, e.g. to hand the keys this Node no
+    /// longer owns over to their new owners.
+    fn rebalance(
+        &mut self,
+        _old: &HashRing,
+        _new: &HashRing,
+        _state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        Vec::with_capacity(0)
+    }
+}
+
+/// Partitions the keys of the inner handler over the Server Nodes with a HashRing.
+/// Requests for keys owned by another Node are forwarded to the owner, whose reply is relayed
+/// back to the Client through this Node by the PacketHandler.
+/// NOTE: The ring is built from the Server Nodes on the first Message. Membership changes
+/// have to be applied on every Node with set_members, otherwise the Nodes disagree about the
+/// owners and forward requests back and forth.
+pub struct ShardedHandler<H: Sharded> {
+    pub inner: H,
+    pub ring: HashRing,
+    pub virtual_nodes: usize,
+}
+
+impl<H: Sharded> ShardedHandler<H> {
+    pub fn new(inner: H, virtual_nodes: usize) -> Self {
+        Self {
+            inner,
+            ring: HashRing::default(),
+            virtual_nodes,
+        }
+    }
+    /// Rebuilds the ring with the members and lets the inner handler rebalance its keys.
+    pub fn set_members(
+        &mut self,
+        members: impl IntoIterator<Item = NodeId>,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        // NOTE: The ring has to be built before, otherwise the new ring would not be
+        // rebalanced against the Server Nodes.
+        let mut responses = self.init_ring(state);
+        responses.extend(self.replace_ring(members, state));
+        responses
+    }
+    fn init_ring(&mut self, state: &NodeInfo) -> Vec<MessageResponse> {
+        match self.ring.members.is_empty() {
+            true => self.replace_ring(state.server_nodes.iter().cloned(), state),
+            false => Vec::with_capacity(0),
+        }
+    }
+    fn replace_ring(
+        &mut self,
+        members: impl IntoIterator<Item = NodeId>,
+        state: &NodeInfo,
+    ) -> Vec<MessageResponse> {
+        let old = std::mem::replace(&mut self.ring, HashRing::new(members, self.virtual_nodes));
+        self.inner.rebalance(&old, &self.ring, state)
+    }
+}
+
+impl<H: Sharded> MessageHandler for ShardedHandler<H> {
+    fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse> {
+        let responses = Collection::from(self.init_ring(state));
+        let owner = self
+            .inner
+            .shard_key(&packet.body.payload)
+            .and_then(|key| self.ring.owner(&key));
+        match owner {
+            Some(owner) if owner != state.node_id => {
+                responses
+                    + Collection::One(MessageResponse::NoAck {
+                        src: Option::None,
+                        dest: owner,
+                        in_reply_to: Option::None,
+                        payload: Payload::Forward {
+                            packet: Box::new(Packet {
+                                src: packet.src,
+                                dest: owner,
+                                body: packet.body.clone(),
+                            }),
+                        },
+                    })
+            }
+            _ => responses + self.inner.handle_message(packet, state),
+        }
+    }
+    fn handle_tick(&mut self, state: &NodeInfo) -> Collection<MessageResponse> {
+        Collection::from(self.init_ring(state)) + self.inner.handle_tick(state)
+    }
+}
@@ -0,0 +1,246 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Write,
+};
+
+use crate::types::{
+    helpers::{build_broadcast_topology, build_redundant_broadcast_topology},
+    node_id::NodeId,
+    topology::{BroadcastTopology, IndexedTopology, Topology},
+};
+
+/// The Broadcast Topologies of all Nodes, indexed by the Node Id of the forwarding Node.
+pub type BroadcastTopologies = HashMap<NodeId, BroadcastTopology>;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TopologyStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    /// Longest shortest path between two Nodes, None if the Topology is disconnected.
+    pub diameter: Option<usize>,
+    pub degrees: BTreeMap<NodeId, usize>,
+    /// Average number of hops until a Broadcast reaches a Node.
+    pub expected_hops: f64,
+    /// Number of Broadcast messages received by Nodes that already had the Broadcast, summed
+    /// over all origins.
+    pub redundant_deliveries: usize,
+    pub broadcasts: BTreeMap<NodeId, BroadcastStats>,
+}
+
+/// Result of simulating a Broadcast from one origin, where every Node forwards the Broadcast
+/// once when it first receives it and every message takes one hop.
+#[derive(Clone, Debug, Serialize)]
+pub struct BroadcastStats {
+    /// Number of messages sent between Nodes.
+    pub messages: usize,
+    /// Nodes that did not receive the Broadcast.
+    pub missing: Vec<NodeId>,
+    pub max_hops: usize,
+    pub expected_hops: f64,
+    pub redundant_deliveries: usize,
+}
+
+/// NOTE: The maps are ordered by the Node Ids, so the export is deterministic.
+#[derive(Clone, Debug, Serialize)]
+pub struct TopologyExport<'a> {
+    pub topology: BTreeMap<&'a NodeId, &'a Vec<NodeId>>,
+    pub broadcast_topologies: BTreeMap<&'a NodeId, BTreeMap<&'a NodeId, &'a Vec<NodeId>>>,
+    pub stats: TopologyStats,
+}
+
+/// Builds the Broadcast Topology of every Node like the PacketHandler would.
+pub fn build_broadcast_topologies(topology: &Topology, paths: usize) -> BroadcastTopologies {
+    let nodes = sorted_nodes(topology);
+    let indexed = IndexedTopology::new(&nodes, topology);
+    nodes
+        .iter()
+        .map(|node| {
+            let broadcast_topology = match paths {
+                0 | 1 => build_broadcast_topology(node, &indexed),
+                paths => build_redundant_broadcast_topology(node, &indexed, paths),
+            };
+            (*node, broadcast_topology)
+        })
+        .collect()
+}
+
+pub fn analyze_topology(
+    topology: &Topology,
+    broadcast_topologies: &BroadcastTopologies,
+) -> TopologyStats {
+    let nodes = sorted_nodes(topology);
+
+    let degrees: BTreeMap<NodeId, usize> = nodes
+        .iter()
+        .map(|n| (*n, topology.get(n).map_or(0, |a| a.len())))
+        .collect();
+    let edge_count = edges(topology).len();
+
+    let mut diameter = Some(0);
+    for node in nodes.iter() {
+        let distances = hop_distances(node, |n| topology.get(n).cloned().unwrap_or_default());
+        diameter = match distances.len() == nodes.len() {
+            true => diameter.max(distances.values().max().cloned()),
+            false => None,
+        };
+        if diameter.is_none() {
+            break;
+        }
+    }
+
+    let broadcasts: BTreeMap<NodeId, BroadcastStats> = nodes
+        .iter()
+        .map(|origin| {
+            let stats = simulate_broadcast(&nodes, origin, broadcast_topologies);
+            (*origin, stats)
+        })
+        .collect();
+
+    let expected_hops = match broadcasts.is_empty() {
+        true => 0.0,
+        false => {
+            broadcasts.values().map(|s| s.expected_hops).sum::<f64>() / broadcasts.len() as f64
+        }
+    };
+
+    TopologyStats {
+        node_count: nodes.len(),
+        edge_count,
+        diameter,
+        degrees,
+        expected_hops,
+        redundant_deliveries: broadcasts.values().map(|s| s.redundant_deliveries).sum(),
+        broadcasts,
+    }
+}
+
+fn simulate_broadcast(
+    nodes: &[NodeId],
+    origin: &NodeId,
+    broadcast_topologies: &BroadcastTopologies,
+) -> BroadcastStats {
+    let mut messages = 0;
+    let hops = hop_distances(origin, |node| {
+        let forward = broadcast_topologies
+            .get(node)
+            .and_then(|t| t.get(origin))
+            .cloned()
+            .unwrap_or_default();
+        messages += forward.len();
+        forward
+    });
+
+    let missing: Vec<NodeId> = nodes
+        .iter()
+        .filter(|n| !hops.contains_key(*n))
+        .cloned()
+        .collect();
+    let expected_hops = match hops.len() {
+        0 | 1 => 0.0,
+        received => hops.values().sum::<usize>() as f64 / (received - 1) as f64,
+    };
+
+    BroadcastStats {
+        messages,
+        missing,
+        max_hops: hops.values().max().cloned().unwrap_or(0),
+        expected_hops,
+        redundant_deliveries: messages + 1 - hops.len(),
+    }
+}
+
+/// Breadth-first search from start.
+/// Returns the number of hops to every reachable Node.
+fn hop_distances<F>(start: &NodeId, mut neighbours: F) -> HashMap<NodeId, usize>
+where
+    F: FnMut(&NodeId) -> Vec<NodeId>,
+{
+    let mut distances = HashMap::from([(*start, 0)]);
+    let mut queue = VecDeque::from([*start]);
+
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        for neighbour in neighbours(&node) {
+            if let std::collections::hash_map::Entry::Vacant(e) = distances.entry(neighbour) {
+                e.insert(distance + 1);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    distances
+}
+
+pub fn topology_to_dot(topology: &Topology) -> String {
+    let mut dot = String::from("graph topology {\n");
+    for node in sorted_nodes(topology) {
+        let _ = writeln!(dot, "    \"{}\";", node);
+    }
+    for (n1, n2) in edges(topology) {
+        let _ = writeln!(dot, "    \"{}\" -- \"{}\";", n1, n2);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Exports the paths Broadcasts from origin are forwarded along.
+pub fn broadcast_topology_to_dot(
+    origin: &NodeId,
+    broadcast_topologies: &BroadcastTopologies,
+) -> String {
+    let mut dot = format!("digraph \"broadcast_{}\" {{\n", origin);
+    let _ = writeln!(dot, "    \"{}\" [shape=doublecircle];", origin);
+
+    let mut nodes: Vec<&NodeId> = broadcast_topologies.keys().collect();
+    nodes.sort();
+    for node in nodes {
+        for next in broadcast_topologies[node].get(origin).into_iter().flatten() {
+            let _ = writeln!(dot, "    \"{}\" -> \"{}\";", node, next);
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn topology_to_json(
+    topology: &Topology,
+    broadcast_topologies: &BroadcastTopologies,
+) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&TopologyExport {
+        topology: topology.iter().collect(),
+        broadcast_topologies: broadcast_topologies
+            .iter()
+            .map(|(node, broadcast_topology)| (node, broadcast_topology.iter().collect()))
+            .collect(),
+        stats: analyze_topology(topology, broadcast_topologies),
+    })
+}
+
+/// Returns all Nodes in the Topology (including Nodes that only appear as neighbours) sorted
+/// by their Node Id.
+fn sorted_nodes(topology: &Topology) -> Vec<NodeId> {
+    let nodes: HashSet<&NodeId> = topology
+        .iter()
+        .flat_map(|(n, neighbours)| std::iter::once(n).chain(neighbours.iter()))
+        .collect();
+    let mut nodes: Vec<NodeId> = nodes.into_iter().cloned().collect();
+    nodes.sort();
+    nodes
+}
+
+/// Returns every undirected link once, sorted by the Node Ids.
+fn edges(topology: &Topology) -> Vec<(&NodeId, &NodeId)> {
+    let mut edges: Vec<(&NodeId, &NodeId)> = topology
+        .iter()
+        .flat_map(|(n1, neighbours)| {
+            neighbours.iter().map(move |n2| match n1 <= n2 {
+                true => (n1, n2),
+                false => (n2, n1),
+            })
+        })
+        .collect();
+    edges.sort();
+    edges.dedup();
+    edges
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::node_id::NodeId;
+
+pub mod coordinator;
+pub mod participant;
+
+/// Transactions are numbered by their coordinator, so participants know whom to ask for the
+/// decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TxnId {
+    pub coordinator: NodeId,
+    pub number: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Decision {
+    Commit,
+    Abort,
+}
+
+/// The part of a transaction a participant executes (e.g. the keys of its shard).
+pub trait Resource {
+    /// Validates the operations and keeps them (and e.g. their locks) until the decision.
+    /// Returns false to vote for aborting the transaction.
+    fn prepare(&mut self, txn_id: &TxnId, operations: &serde_json::Value) -> bool;
+    /// Applies the operations of the prepared transaction.
+    fn commit(&mut self, txn_id: &TxnId);
+    /// Discards the operations of the transaction, which may not have been prepared.
+    fn abort(&mut self, txn_id: &TxnId);
+}
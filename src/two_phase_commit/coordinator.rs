@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    two_phase_commit::{Decision, TxnId},
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_id::NodeId, node_info::NodeInfo, packet::Packet,
+        payload::Payload,
+    },
+};
+
+/// Transaction waiting for the votes of its participants.
+pub struct PendingTxn {
+    /// The votes (true to commit) of the participants by their Node.
+    pub votes: HashMap<NodeId, Option<bool>>,
+    pub started: Instant,
+}
+
+/// Coordinator of two-phase commits.
+/// Prepares are sent with Ack, so they are resent by the PacketHandler until the participant
+/// has received them.
+/// The transaction is aborted if a participant votes to abort or not all votes have arrived
+/// within the prepare timeout. The decision is recorded in the decision log before it is sent
+/// (with Ack) to the participants.
+/// Requires the PacketHandler to have a tick interval and a resend timeout (see
+/// DeliveryConfig), otherwise a lost prepare or decision is only resent after a SyncRequest.
+pub struct Coordinator {
+    pub next_number: u64,
+    pub pending: HashMap<TxnId, PendingTxn>,
+    /// Decision log, used to answer the recovery queries of participants.
+    /// NOTE: Transactions that are not in the log and not pending are presumed to be aborted.
+    pub decisions: HashMap<TxnId, Decision>,
+    /// Decisions that have not been taken yet (see Coordinator::take_decided).
+    pub decided: Vec<(TxnId, Decision)>,
+    pub prepare_timeout: Duration,
+}
+
+impl Coordinator {
+    pub fn new(prepare_timeout: Duration) -> Self {
+        Self {
+            next_number: 0,
+            pending: HashMap::new(),
+            decisions: HashMap::new(),
+            decided: Vec::new(),
+            prepare_timeout,
+        }
+    }
+    /// Starts a transaction with the operations of every participant.
+    pub fn begin(
+        &mut self,
+        operations: HashMap<NodeId, serde_json::Value>,
+        state: &NodeInfo,
+    ) -> (TxnId, Vec<MessageResponse>) {
+        self.next_number += 1;
+        let txn_id = TxnId {
+            coordinator: state.node_id,
+            number: self.next_number,
+        };
+        self.pending.insert(
+            txn_id,
+            PendingTxn {
+                votes: operations.keys().map(|n| (*n, None)).collect(),
+                started: Instant::now(),
+            },
+        );
+        let responses = operations
+            .into_iter()
+            .map(|(participant, operations)| MessageResponse::Ack {
+                src: Option::None,
+                dest: participant,
+                in_reply_to: Option::None,
+                payload: Payload::TxnPrepare { txn_id, operations },
+            })
+            .collect();
+        (txn_id, responses)
+    }
+    /// Returns the transactions that have been decided since the last call.
+    pub fn take_decided(&mut self) -> Vec<(TxnId, Decision)> {
+        std::mem::take(&mut self.decided)
+    }
+    fn decide(&mut self, txn_id: TxnId, decision: Decision) -> Vec<MessageResponse> {
+        let Some(txn) = self.pending.remove(&txn_id) else {
+            return Vec::with_capacity(0);
+        };
+        self.decisions.insert(txn_id, decision);
+        self.decided.push((txn_id, decision));
+        txn.votes
+            .into_keys()
+            .map(|participant| MessageResponse::Ack {
+                src: Option::None,
+                dest: participant,
+                in_reply_to: Option::None,
+                payload: Self::decision_payload(txn_id, decision),
+            })
+            .collect()
+    }
+    fn decision_payload(txn_id: TxnId, decision: Decision) -> Payload {
+        match decision {
+            Decision::Commit => Payload::TxnCommit { txn_id },
+            Decision::Abort => Payload::TxnAbort { txn_id },
+        }
+    }
+}
+
+impl MessageHandler for Coordinator {
+    fn handle_message(
+        &mut self,
+        packet: &Packet,
+        _state: &NodeInfo,
+    ) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::TxnVote { txn_id, commit },
+                        ..
+                    },
+                ..
+            } => {
+                let Some(txn) = self.pending.get_mut(txn_id) else {
+                    return Collection::None;
+                };
+                if let Some(vote) = txn.votes.get_mut(src) {
+                    *vote = Some(*commit);
+                }
+                if !commit {
+                    self.decide(*txn_id, Decision::Abort).into()
+                } else if txn.votes.values().all(|v| *v == Some(true)) {
+                    self.decide(*txn_id, Decision::Commit).into()
+                } else {
+                    Collection::None
+                }
+            }
+            // NOTE: Pending transactions are not answered, the participant asks again later.
+            Packet {
+                src,
+                body:
+                    Message {
+                        msg_id,
+                        payload: Payload::TxnQuery { txn_id },
+                        ..
+                    },
+                ..
+            } if !self.pending.contains_key(txn_id) => {
+                let decision = self
+                    .decisions
+                    .get(txn_id)
+                    .cloned()
+                    .unwrap_or(Decision::Abort);
+                Collection::One(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: *src,
+                    in_reply_to: *msg_id,
+                    payload: Self::decision_payload(*txn_id, decision),
+                })
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+        let timed_out: Vec<TxnId> = self
+            .pending
+            .iter()
+            .filter(|(_, txn)| txn.started.elapsed() >= self.prepare_timeout)
+            .map(|(txn_id, _)| *txn_id)
+            .collect();
+        timed_out
+            .into_iter()
+            .flat_map(|txn_id| self.decide(txn_id, Decision::Abort))
+            .collect::<Vec<MessageResponse>>()
+            .into()
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    two_phase_commit::{Decision, Resource, TxnId},
+    types::{
+        collection::Collection, message::Message, message_handler::MessageHandler,
+        message_response::MessageResponse, node_info::NodeInfo, packet::Packet, payload::Payload,
+    },
+};
+
+/// Participant of two-phase commits.
+/// Votes are sent with Ack, so they are resent by the PacketHandler until the coordinator has
+/// received them. A participant that has voted to commit can not decide on its own, so it asks
+/// the coordinator for the decision every query timeout until it has received it.
+/// Requires the PacketHandler to have a tick interval and a resend timeout (see
+/// DeliveryConfig).
+pub struct Participant<R: Resource> {
+    pub resource: R,
+    /// Transactions that have been prepared and are waiting for the decision, with the time
+    /// they were prepared or last queried.
+    pub prepared: HashMap<TxnId, Instant>,
+    /// Decision log, so retried prepares and decisions are not applied again.
+    pub decisions: HashMap<TxnId, Decision>,
+    pub query_timeout: Duration,
+}
+
+impl<R: Resource> Participant<R> {
+    pub fn new(resource: R, query_timeout: Duration) -> Self {
+        Self {
+            resource,
+            prepared: HashMap::new(),
+            decisions: HashMap::new(),
+            query_timeout,
+        }
+    }
+    fn vote(&mut self, txn_id: &TxnId, operations: &serde_json::Value) -> bool {
+        match self.decisions.get(txn_id) {
+            Some(decision) => *decision == Decision::Commit,
+            None if self.prepared.contains_key(txn_id) => true,
+            None => {
+                let commit = self.resource.prepare(txn_id, operations);
+                match commit {
+                    true => {
+                        self.prepared.insert(*txn_id, Instant::now());
+                    }
+                    false => {
+                        self.resource.abort(txn_id);
+                        self.decisions.insert(*txn_id, Decision::Abort);
+                    }
+                }
+                commit
+            }
+        }
+    }
+    fn apply(&mut self, txn_id: &TxnId, decision: Decision) {
+        if self.decisions.contains_key(txn_id) {
+            return;
+        }
+        match (self.prepared.remove(txn_id), decision) {
+            (Some(_), Decision::Commit) => self.resource.commit(txn_id),
+            (_, Decision::Abort) => self.resource.abort(txn_id),
+            // NOTE: Only transactions every participant has prepared are committed.
+            (None, Decision::Commit) => return,
+        }
+        self.decisions.insert(*txn_id, decision);
+    }
+}
+
+impl<R: Resource> MessageHandler for Participant<R> {
+    fn handle_message(
+        &mut self,
+        packet: &Packet,
+        _state: &NodeInfo,
+    ) -> Collection<MessageResponse> {
+        match packet {
+            Packet {
+                src,
+                body:
+                    Message {
+                        payload: Payload::TxnPrepare { txn_id, operations },
+                        ..
+                    },
+                ..
+            } => Collection::One(MessageResponse::Ack {
+                src: Option::None,
+                dest: *src,
+                in_reply_to: Option::None,
+                payload: Payload::TxnVote {
+                    txn_id: *txn_id,
+                    commit: self.vote(txn_id, operations),
+                },
+            }),
+            Packet {
+                body:
+                    Message {
+                        payload: Payload::TxnCommit { txn_id },
+                        ..
+                    },
+                ..
+            } => {
+                self.apply(txn_id, Decision::Commit);
+                Collection::None
+            }
+            Packet {
+                body:
+                    Message {
+                        payload: Payload::TxnAbort { txn_id },
+                        ..
+                    },
+                ..
+            } => {
+                self.apply(txn_id, Decision::Abort);
+                Collection::None
+            }
+            _ => Collection::None,
+        }
+    }
+    fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+        let mut responses = Vec::new();
+        for (txn_id, last_query) in self.prepared.iter_mut() {
+            if last_query.elapsed() >= self.query_timeout {
+                *last_query = Instant::now();
+                responses.push(MessageResponse::NoAck {
+                    src: Option::None,
+                    dest: txn_id.coordinator,
+                    in_reply_to: Option::None,
+                    payload: Payload::TxnQuery { txn_id: *txn_id },
+                });
+            }
+        }
+        responses.into()
+    }
+}
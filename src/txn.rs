@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+pub mod hlc;
+pub mod mvcc;
+
+/// Micro-operation of the "txn-list-append" workload.
+/// Serialized as ["r", key, value] and ["append", key, element].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "(String, i64, serde_json::Value)",
+    into = "(String, i64, serde_json::Value)"
+)]
+pub enum MicroOp {
+    /// The value is None in requests and the list that has been read in replies.
+    Read {
+        key: i64,
+        value: Option<Vec<i64>>,
+    },
+    Append {
+        key: i64,
+        element: i64,
+    },
+}
+
+impl TryFrom<(String, i64, serde_json::Value)> for MicroOp {
+    type Error = String;
+
+    fn try_from((op, key, value): (String, i64, serde_json::Value)) -> Result<Self, Self::Error> {
+        match op.as_str() {
+            "r" => Ok(MicroOp::Read {
+                key,
+                value: serde_json::from_value(value).map_err(|e| e.to_string())?,
+            }),
+            "append" => Ok(MicroOp::Append {
+                key,
+                element: value
+                    .as_i64()
+                    .ok_or_else(|| format!("Expected an integer, got {}", value))?,
+            }),
+            op => Err(format!("Unknown micro-operation {}", op)),
+        }
+    }
+}
+
+impl From<MicroOp> for (String, i64, serde_json::Value) {
+    fn from(op: MicroOp) -> Self {
+        match op {
+            MicroOp::Read { key, value } => ("r".to_string(), key, serde_json::json!(value)),
+            MicroOp::Append { key, element } => ("append".to_string(), key, element.into()),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::id_strategy::{Clock, SystemClock};
+
+/// Timestamp of a HybridClock.
+/// Timestamps are ordered by their physical time, then their logical counter and then the
+/// number of the Node, so the timestamps of different Nodes never collide.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Timestamp {
+    pub millis: u64,
+    pub logical: u64,
+    pub node_number: usize,
+}
+
+/// Hybrid logical clock.
+/// Follows the physical clock while it advances, but never returns a timestamp that is not
+/// greater than every timestamp it has returned or observed before (e.g. in Messages of other
+/// Nodes whose clocks are ahead).
+pub struct HybridClock {
+    clock: Box<dyn Clock>,
+    pub last: Timestamp,
+}
+
+impl Default for HybridClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HybridClock {
+    pub fn new() -> Self {
+        Self::with_clock(Box::new(SystemClock))
+    }
+    pub fn with_clock(clock: Box<dyn Clock>) -> Self {
+        Self {
+            clock,
+            last: Timestamp::default(),
+        }
+    }
+    pub fn now(&mut self, node_number: usize) -> Timestamp {
+        let millis = self.clock.now_millis();
+        self.last = match millis > self.last.millis {
+            true => Timestamp {
+                millis,
+                logical: 0,
+                node_number,
+            },
+            false => Timestamp {
+                millis: self.last.millis,
+                logical: self.last.logical + 1,
+                node_number,
+            },
+        };
+        self.last
+    }
+    pub fn observe(&mut self, timestamp: Timestamp) {
+        self.last = self.last.max(timestamp);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::txn::{hlc::Timestamp, MicroOp};
+
+/// Multi-version storage of lists that are only appended to.
+/// Every committed transaction adds a version with the elements it appended, so the list at a
+/// timestamp consists of the elements of all versions committed up to that timestamp.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MvccStore {
+    /// The appended elements of every version by their key and commit timestamp.
+    pub versions: HashMap<i64, BTreeMap<Timestamp, Vec<i64>>>,
+}
+
+impl MvccStore {
+    pub fn read(&self, key: i64, snapshot: Timestamp) -> Vec<i64> {
+        self.versions
+            .get(&key)
+            .map(|versions| {
+                versions
+                    .range(..=snapshot)
+                    .flat_map(|(_, elements)| elements.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    pub fn last_commit(&self, key: i64) -> Option<Timestamp> {
+        self.versions
+            .get(&key)
+            .and_then(|versions| versions.keys().next_back().cloned())
+    }
+    /// Adds the versions of a transaction that has been committed at the timestamp (e.g. by
+    /// another Node).
+    pub fn install(&mut self, commit_ts: Timestamp, appends: &BTreeMap<i64, Vec<i64>>) {
+        for (key, elements) in appends {
+            self.versions
+                .entry(*key)
+                .or_default()
+                .insert(commit_ts, elements.clone());
+        }
+    }
+    /// Commits the transaction unless another transaction has committed to one of the keys it
+    /// appended to since it started (first committer wins).
+    /// Returns the conflicting key otherwise.
+    pub fn commit(&mut self, txn: &Transaction, commit_ts: Timestamp) -> Result<(), i64> {
+        if let Some(key) = txn
+            .appends
+            .keys()
+            .find(|key| self.last_commit(**key).is_some_and(|ts| ts > txn.start_ts))
+        {
+            return Err(*key);
+        }
+        self.install(commit_ts, &txn.appends);
+        Ok(())
+    }
+}
+
+/// The elements a transaction appended and the timestamp it has been committed at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub commit_ts: Timestamp,
+    pub appends: BTreeMap<i64, Vec<i64>>,
+}
+
+/// Transaction reading from the snapshot at its start timestamp.
+/// The appends are buffered until the transaction is committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub start_ts: Timestamp,
+    pub appends: BTreeMap<i64, Vec<i64>>,
+}
+
+impl Transaction {
+    pub fn new(start_ts: Timestamp) -> Self {
+        Self {
+            start_ts,
+            appends: BTreeMap::new(),
+        }
+    }
+    /// Executes the micro-operations and returns them with the values that have been read.
+    /// Reads see the snapshot and the earlier appends of this transaction.
+    pub fn execute(&mut self, store: &MvccStore, ops: &[MicroOp]) -> Vec<MicroOp> {
+        ops.iter()
+            .map(|op| match op {
+                MicroOp::Read { key, .. } => {
+                    let mut value = store.read(*key, self.start_ts);
+                    value.extend(self.appends.get(key).into_iter().flatten());
+                    MicroOp::Read {
+                        key: *key,
+                        value: Some(value),
+                    }
+                }
+                MicroOp::Append { key, element } => {
+                    self.appends.entry(*key).or_default().push(*element);
+                    op.clone()
+                }
+            })
+            .collect()
+    }
+}
@@ -4,7 +4,11 @@ pub mod packet;
 pub mod payload;
 
 pub mod collection;
+pub mod delivery_config;
 pub mod helpers;
+pub mod id_strategy;
 pub mod message_response;
+pub mod message_store;
+pub mod node_id;
 pub mod node_info;
 pub mod topology;
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+/// Delivery guarantees the PacketHandler gives to its MessageHandlers.
+#[derive(Clone, Debug, Default)]
+pub struct DeliveryConfig {
+    /// Treat the link to every Server Node as a FIFO channel:
+    /// Messages with a msg_id are delivered in the order they were sent. Messages that arrive
+    /// after a gap are buffered per sender until the missing Messages have been received.
+    pub fifo: bool,
+    /// Deliver every Message exactly once (implies fifo):
+    ///     - Messages from Server Nodes are delivered in order and without duplicates.
+    ///     - Retried Client Requests (same "src" and "msg_id") are not handled again, instead
+    ///       the responses of the first request are resent.
+    pub exactly_once: bool,
+    /// Number of handled requests that are remembered per Client Node (at least 1).
+    pub client_window: usize,
+    /// Messages sent with Ack are resent on tick if they have not been acknowledged within
+    /// the timeout. Without it, they are only resent when the receiver notices the gap and
+    /// sends a SyncRequest, so a lost last Message is never resent.
+    pub resend_timeout: Option<Duration>,
+}
+
+impl DeliveryConfig {
+    pub fn fifo() -> Self {
+        Self {
+            fifo: true,
+            ..Default::default()
+        }
+    }
+    /// Panics if the client_window is 0, because no requests could be replayed.
+    pub fn exactly_once(client_window: usize) -> Self {
+        assert!(client_window > 0, "The client window must be at least 1");
+        Self {
+            fifo: true,
+            exactly_once: true,
+            client_window,
+            ..Default::default()
+        }
+    }
+    pub fn set_resend_timeout(&mut self, resend_timeout: Duration) {
+        self.resend_timeout = Some(resend_timeout);
+    }
+    pub fn is_fifo(&self) -> bool {
+        self.fifo || self.exactly_once
+    }
+}
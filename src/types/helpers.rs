@@ -1,59 +1,295 @@
-use crate::types::topology::Topology;
-use std::collections::{HashMap, HashSet};
+use crate::types::{
+    node_id::NodeId,
+    topology::{BroadcastTopology, IndexedTopology, Topology, TopologyStrategy},
+};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-pub(crate) fn build_broadcast_topology(
-    own_node_id: &String,
-    all_nodes: &Vec<String>,
+/// Derives the Topology that should be used according to the strategy.
+pub(crate) fn build_topology(
+    strategy: &TopologyStrategy,
+    all_nodes: &[NodeId],
     topology: &Topology,
-) -> HashMap<String, Vec<String>> {
-    let num_nodes = all_nodes.len();
+) -> Topology {
+    let mut nodes = all_nodes.to_vec();
+    nodes.sort();
+
+    let mut edges: Vec<(&NodeId, &NodeId)> = Vec::new();
+    match strategy {
+        TopologyStrategy::AsGiven => return topology.clone(),
+        TopologyStrategy::SpanningTree => {
+            let mut visited: HashSet<&NodeId> = HashSet::with_capacity(nodes.len());
+            let mut queue: VecDeque<&NodeId> = nodes.first().into_iter().collect();
+            visited.extend(nodes.first());
+
+            while let Some(node) = queue.pop_front() {
+                let mut neighbours: Vec<&NodeId> = topology
+                    .get(node)
+                    .map(|n| n.iter().collect())
+                    .unwrap_or_default();
+                neighbours.sort();
+
+                for neighbour in neighbours {
+                    if visited.insert(neighbour) {
+                        edges.push((node, neighbour));
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+        TopologyStrategy::Star => {
+            edges.extend(nodes.iter().skip(1).map(|n| (&nodes[0], n)));
+        }
+        TopologyStrategy::KAryTree(k) => {
+            let k = (*k).max(1);
+            edges.extend(
+                nodes
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(idx, n)| (&nodes[(idx - 1) / k], n)),
+            );
+        }
+        TopologyStrategy::FullyConnected => {
+            for (idx, n1) in nodes.iter().enumerate() {
+                edges.extend(nodes.iter().skip(idx + 1).map(|n2| (n1, n2)));
+            }
+        }
+    }
 
-    let mut broadcast_topology: HashMap<String, Vec<String>> = HashMap::new();
+    let mut built: Topology = nodes.iter().map(|n| (*n, Vec::new())).collect();
+    for (n1, n2) in edges {
+        built.get_mut(n1).unwrap().push(*n2);
+        built.get_mut(n2).unwrap().push(*n1);
+    }
+    built
+}
 
-    for node_id in all_nodes {
-        let mut already_visited: HashSet<String> = HashSet::with_capacity(num_nodes);
+/// Splits the Nodes into the connected components of the Topology.
+/// The Node Ids in each component and the components (by their lowest Node Id) are sorted.
+pub(crate) fn connected_components(all_nodes: &[NodeId], topology: &Topology) -> Vec<Vec<NodeId>> {
+    let mut nodes = all_nodes.to_vec();
+    nodes.sort();
 
-        let mut neighbours: HashSet<String> = topology[own_node_id].clone().into_iter().collect();
-        neighbours.remove(node_id);
+    let mut visited: HashSet<&NodeId> = HashSet::with_capacity(nodes.len());
+    let mut components = Vec::new();
 
-        if node_id == own_node_id {
-            broadcast_topology.insert(node_id.clone(), neighbours.into_iter().collect());
+    for node in nodes.iter() {
+        if !visited.insert(node) {
             continue;
         }
 
-        let mut found_self = false;
-        let mut stack: HashSet<String> = HashSet::from([node_id.clone()]);
-        // TODO: If The Stack is empty condition is met, then there probably is two or more non-connected sub-graphs
-        while !found_self
-            && already_visited.len() < num_nodes
-            && !stack.is_empty()
-            && !neighbours.is_empty()
-        {
-            let mut temp: HashSet<String>;
-
-            if stack.contains(own_node_id) {
-                temp = stack
-                    .iter()
-                    .filter(|n| is_lower_node_id(n, own_node_id))
-                    .flat_map(|n| topology[n].clone())
-                    .collect();
-                found_self = true;
-            } else {
-                temp = stack.iter().flat_map(|n| topology[n].clone()).collect();
+        let mut component = vec![*node];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            for neighbour in topology.get(node).into_iter().flatten() {
+                if visited.insert(neighbour) {
+                    component.push(*neighbour);
+                    stack.push(neighbour);
+                }
             }
+        }
+
+        component.sort();
+        components.push(component);
+    }
+
+    components
+}
+
+/// Connects the components by adding an edge between the lowest Node Ids of consecutive
+/// components.
+/// Returns the added edges.
+pub(crate) fn repair_topology(
+    topology: &mut Topology,
+    components: &[Vec<NodeId>],
+) -> Vec<(NodeId, NodeId)> {
+    let bridges: Vec<(NodeId, NodeId)> =
+        components.windows(2).map(|c| (c[0][0], c[1][0])).collect();
+
+    for (n1, n2) in bridges.iter() {
+        topology.entry(*n1).or_default().push(*n2);
+        topology.entry(*n2).or_default().push(*n1);
+    }
+
+    bridges
+}
+
+/// NOTE: The Topology has to be connected, otherwise the Broadcast Topology for Nodes in other
+/// components is incomplete (see connected_components and repair_topology).
+pub(crate) fn build_broadcast_topology(
+    own_node_id: &NodeId,
+    topology: &IndexedTopology,
+) -> BroadcastTopology {
+    let own = match topology.index_of(own_node_id) {
+        Some(own) => own,
+        None => return HashMap::new(),
+    };
+    let num_nodes = topology.len();
+    let own_neighbours = &topology.adjacency[own];
 
-            temp = temp.difference(&already_visited).cloned().collect();
-            neighbours = neighbours.difference(&temp).cloned().collect();
-            already_visited.extend(stack);
-            stack = temp;
+    // NOTE: The buffers are reused for every origin, so only the result is allocated.
+    let mut already_visited = vec![false; num_nodes];
+    let mut in_temp = vec![false; num_nodes];
+    let mut is_neighbour = vec![false; num_nodes];
+    let mut stack: Vec<usize> = Vec::with_capacity(num_nodes);
+    let mut temp: Vec<usize> = Vec::with_capacity(num_nodes);
+
+    let mut broadcast_topology: BroadcastTopology = HashMap::with_capacity(num_nodes);
+
+    for origin in 0..num_nodes {
+        already_visited.fill(false);
+        for &n in own_neighbours {
+            is_neighbour[n] = n != origin;
+        }
+        let mut num_neighbours = own_neighbours.iter().filter(|n| is_neighbour[**n]).count();
+        let mut num_visited = 0;
+        let mut found_self = origin == own;
+
+        stack.clear();
+        stack.push(origin);
+        // NOTE: If the Stack is empty the Topology consists of two or more non-connected
+        // sub-graphs, which the PacketHandler detects before building the Broadcast Topology.
+        while !found_self && num_visited < num_nodes && !stack.is_empty() && num_neighbours > 0 {
+            // NOTE: Only the Nodes with a lower Node Id (= index) on the same level as this
+            // Node forward the Broadcast before this Node.
+            found_self = stack.contains(&own);
+
+            temp.clear();
+            for &n in stack.iter().filter(|n| !found_self || **n < own) {
+                for &next in topology.adjacency[n].iter() {
+                    if !already_visited[next] && !in_temp[next] {
+                        in_temp[next] = true;
+                        temp.push(next);
+                    }
+                }
+            }
+            for &n in temp.iter() {
+                in_temp[n] = false;
+                if is_neighbour[n] {
+                    is_neighbour[n] = false;
+                    num_neighbours -= 1;
+                }
+            }
+            for &n in stack.iter() {
+                if !already_visited[n] {
+                    already_visited[n] = true;
+                    num_visited += 1;
+                }
+            }
+            std::mem::swap(&mut stack, &mut temp);
         }
 
-        broadcast_topology.insert(node_id.clone(), neighbours.into_iter().collect());
+        let forward = own_neighbours
+            .iter()
+            .filter(|n| is_neighbour[**n])
+            .map(|n| topology.nodes[*n])
+            .collect();
+        broadcast_topology.insert(topology.nodes[origin], forward);
+    }
+
+    broadcast_topology
+}
+
+/// Builds a Broadcast Topology where Broadcasts from every origin are sent along (up to) paths
+/// edge-disjoint paths to every other Node.
+/// NOTE: Nodes may receive a Broadcast multiple times, so they have to ignore Broadcasts that
+/// they have already forwarded.
+pub(crate) fn build_redundant_broadcast_topology(
+    own_node_id: &NodeId,
+    topology: &IndexedTopology,
+    paths: usize,
+) -> BroadcastTopology {
+    let own = match topology.index_of(own_node_id) {
+        Some(own) => own,
+        None => return HashMap::new(),
+    };
+
+    let mut broadcast_topology = HashMap::with_capacity(topology.len());
+    for origin in 0..topology.len() {
+        let mut forward: Vec<usize> = (0..topology.len())
+            .filter(|target| *target != origin)
+            .flat_map(|target| edge_disjoint_paths(&topology.adjacency, origin, target, paths))
+            .flat_map(|path| {
+                path.windows(2)
+                    .filter(|w| w[0] == own)
+                    .map(|w| w[1])
+                    .collect::<Vec<usize>>()
+            })
+            .collect();
+        forward.sort_unstable();
+        forward.dedup();
+
+        broadcast_topology.insert(
+            topology.nodes[origin],
+            forward.into_iter().map(|n| topology.nodes[n]).collect(),
+        );
     }
 
     broadcast_topology
 }
 
-pub(crate) fn is_lower_node_id(id1: &str, id2: &str) -> bool {
-    id1[1..].parse::<usize>().unwrap_or(0) < id2[1..].parse::<usize>().unwrap_or(0)
+/// Finds up to k edge-disjoint paths from source to target using augmenting paths (every link
+/// has a capacity of 1 in each direction).
+/// Returns the paths as Node indices starting with source and ending with target.
+fn edge_disjoint_paths(
+    adjacency: &[Vec<usize>],
+    source: usize,
+    target: usize,
+    k: usize,
+) -> Vec<Vec<usize>> {
+    // NOTE: flow[(u, v)] == -flow[(v, u)], so sending flow back along a link cancels it.
+    let mut flow: HashMap<(usize, usize), i32> = HashMap::new();
+    let get_flow = |flow: &HashMap<(usize, usize), i32>, u: usize, v: usize| {
+        flow.get(&(u, v)).cloned().unwrap_or(0)
+    };
+
+    let mut found = 0;
+    while found < k {
+        let mut parent: Vec<Option<usize>> = vec![None; adjacency.len()];
+        let mut queue = VecDeque::from([source]);
+        parent[source] = Some(source);
+
+        while let Some(u) = queue.pop_front() {
+            if u == target {
+                break;
+            }
+            for &v in adjacency[u].iter() {
+                if parent[v].is_none() && get_flow(&flow, u, v) < 1 {
+                    parent[v] = Some(u);
+                    queue.push_back(v);
+                }
+            }
+        }
+        if parent[target].is_none() {
+            break;
+        }
+
+        let mut v = target;
+        while v != source {
+            let u = parent[v].unwrap();
+            *flow.entry((u, v)).or_default() += 1;
+            *flow.entry((v, u)).or_default() -= 1;
+            v = u;
+        }
+        found += 1;
+    }
+
+    // NOTE: Every path follows the links with positive flow, using every link only once.
+    let mut paths = Vec::with_capacity(found);
+    for _ in 0..found {
+        let mut path = vec![source];
+        let mut u = source;
+        while u != target {
+            let v = match adjacency[u].iter().find(|v| get_flow(&flow, u, **v) > 0) {
+                Some(&v) => v,
+                None => break,
+            };
+            flow.insert((u, v), 0);
+            path.push(v);
+            u = v;
+        }
+        paths.push(path);
+    }
+
+    paths
 }
@@ -0,0 +1,173 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 2020-01-01T00:00:00Z in milliseconds since the Unix Epoch.
+pub const SNOWFLAKE_EPOCH: u64 = 1_577_836_800_000;
+/// Number of ids a Node can generate per millisecond with a clock-seeded Striding strategy
+/// before its ids run ahead of the clock.
+pub const STRIDING_IDS_PER_MILLI: u64 = 1024;
+
+/// Source of the current time for time-based ids.
+pub trait Clock {
+    /// Returns the milliseconds since the Unix Epoch.
+    fn now_millis(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+}
+
+/// How the GenerateHandler creates globally unique ids.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// "counter * number of Nodes + node_number".
+    /// Collides if the number of Nodes changes. Restarts from zero after a crash unless the
+    /// counter is seeded from the clock, then every millisecond since the Unix Epoch reserves
+    /// STRIDING_IDS_PER_MILLI ids per Node.
+    Striding { seed_from_clock: bool },
+    /// "timestamp | node_number | sequence" with the milliseconds since epoch in the highest
+    /// bits. Ids are only unique if the Node does not restart within the milliseconds it has
+    /// borrowed (see IdGenerator). Every node_number has to fit into node_bits.
+    Snowflake {
+        epoch: u64,
+        node_bits: u32,
+        sequence_bits: u32,
+    },
+    /// Random UUIDs (122 random bits).
+    UuidV4,
+    /// UUIDs starting with the milliseconds since the Unix Epoch, followed by a 12 bit
+    /// sequence and 62 random bits, so ids of one Node are ordered.
+    UuidV7,
+}
+
+impl IdStrategy {
+    pub fn striding() -> Self {
+        Self::Striding {
+            seed_from_clock: true,
+        }
+    }
+    /// 41 timestamp bits (until 2089), 10 node bits (1024 Nodes) and 12 sequence bits (4096
+    /// ids per millisecond).
+    pub fn snowflake() -> Self {
+        Self::Snowflake {
+            epoch: SNOWFLAKE_EPOCH,
+            node_bits: 10,
+            sequence_bits: 12,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UniqueId {
+    Number(u64),
+    Uuid(String),
+}
+
+/// Generates ids according to an IdStrategy.
+/// NOTE: The time-based strategies never let their timestamp go backwards. If the clock
+/// regresses, the timestamp of the last id is used with the next sequence number, and if the
+/// sequence is exhausted the timestamp is advanced by one millisecond (borrowing from the
+/// future until the clock catches up).
+pub struct IdGenerator {
+    pub strategy: IdStrategy,
+    clock: Box<dyn Clock>,
+    /// Number of generated ids (Striding) or the sequence number of the last id within
+    /// last_timestamp (Snowflake and UUIDv7).
+    pub counter: u64,
+    /// Timestamp of the last generated id (Snowflake and UUIDv7), never decreases.
+    pub last_timestamp: u64,
+}
+
+impl IdGenerator {
+    pub fn new(strategy: IdStrategy) -> Self {
+        Self::with_clock(strategy, Box::new(SystemClock))
+    }
+    pub fn with_clock(strategy: IdStrategy, clock: Box<dyn Clock>) -> Self {
+        Self {
+            strategy,
+            clock,
+            counter: 0,
+            last_timestamp: 0,
+        }
+    }
+    /// Panics if the node_number does not fit into the node_bits of a Snowflake strategy,
+    /// because the ids would collide with the ids of other Nodes.
+    pub fn next_id(&mut self, node_number: usize, num_nodes: usize) -> UniqueId {
+        match self.strategy.clone() {
+            IdStrategy::Striding { seed_from_clock } => {
+                if seed_from_clock {
+                    let seed = self.clock.now_millis() * STRIDING_IDS_PER_MILLI;
+                    self.counter = self.counter.max(seed);
+                }
+                self.counter += 1;
+                UniqueId::Number(self.counter * num_nodes.max(1) as u64 + node_number as u64)
+            }
+            IdStrategy::Snowflake {
+                epoch,
+                node_bits,
+                sequence_bits,
+            } => {
+                let now = self.clock.now_millis().saturating_sub(epoch);
+                self.advance(now, (1 << sequence_bits) - 1);
+
+                let node = node_number as u64;
+                assert!(
+                    node < 1 << node_bits,
+                    "Node number {} does not fit into {} bits",
+                    node_number,
+                    node_bits
+                );
+                UniqueId::Number(
+                    (self.last_timestamp << (node_bits + sequence_bits))
+                        | (node << sequence_bits)
+                        | self.counter,
+                )
+            }
+            IdStrategy::UuidV4 => {
+                let high: u64 = rand::thread_rng().gen();
+                UniqueId::Uuid(Self::format_uuid((high & !0xf000) | 0x4000))
+            }
+            IdStrategy::UuidV7 => {
+                let now = self.clock.now_millis() & ((1 << 48) - 1);
+                self.advance(now, 0xfff);
+                UniqueId::Uuid(Self::format_uuid(
+                    (self.last_timestamp << 16) | 0x7000 | self.counter,
+                ))
+            }
+        }
+    }
+    /// Moves to the next (timestamp, sequence) pair, see IdGenerator.
+    fn advance(&mut self, now: u64, max_sequence: u64) {
+        if now > self.last_timestamp {
+            self.last_timestamp = now;
+            self.counter = 0;
+        } else if self.counter < max_sequence {
+            self.counter += 1;
+        } else {
+            self.last_timestamp += 1;
+            self.counter = 0;
+        }
+    }
+    /// Formats the UUID with the given upper 64 bits (which contain the version) and random
+    /// lower bits.
+    fn format_uuid(high: u64) -> String {
+        let low: u64 = rand::thread_rng().gen();
+        let low = (low & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            high >> 32,
+            (high >> 16) & 0xffff,
+            high & 0xffff,
+            low >> 48,
+            low & 0xffff_ffff_ffff
+        )
+    }
+}
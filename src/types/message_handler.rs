@@ -4,4 +4,8 @@ use crate::types::{
 
 pub trait MessageHandler {
     fn handle_message(&mut self, packet: &Packet, state: &NodeInfo) -> Collection<MessageResponse>;
+    /// Called periodically if the PacketHandler has a tick interval.
+    fn handle_tick(&mut self, _state: &NodeInfo) -> Collection<MessageResponse> {
+        Collection::None
+    }
 }
@@ -1,15 +1,16 @@
-use crate::types::payload::Payload;
+use crate::types::{node_id::NodeId, payload::Payload};
 
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum MessageResponse {
     NoAck {
-        src: Option<String>,
-        dest: String,
+        src: Option<NodeId>,
+        dest: NodeId,
         in_reply_to: Option<usize>,
         payload: Payload,
     },
     Ack {
-        src: Option<String>,
-        dest: String,
+        src: Option<NodeId>,
+        dest: NodeId,
         in_reply_to: Option<usize>,
         payload: Payload,
     },
@@ -19,4 +20,13 @@ pub enum MessageResponse {
     ResponseWithAck {
         payload: Payload,
     },
+    /// Request with a msg_id chosen by the handler, which matches the reply by its
+    /// in_reply_to (e.g. RPCs to Maelstrom services, see the services module).
+    /// NOTE: The PacketHandler does not allocate these msg_ids, so the handler has to be the
+    /// only one sending Requests to the destination.
+    Request {
+        dest: NodeId,
+        msg_id: usize,
+        payload: Payload,
+    },
 }
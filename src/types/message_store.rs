@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+
+/// Set of Broadcast messages that keeps the order in which the messages were inserted.
+#[derive(Clone, Debug, Default)]
+pub struct MessageStore {
+    /// All messages in insertion order.
+    messages: Vec<usize>,
+    known: HashSet<usize>,
+}
+
+impl MessageStore {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Inserts a message.
+    /// Returns false if the message was already known.
+    pub fn insert(&mut self, message: usize) -> bool {
+        if !self.known.insert(message) {
+            return false;
+        }
+        self.messages.push(message);
+        true
+    }
+    /// Inserts all messages that are not known yet.
+    /// Returns the newly inserted messages.
+    pub fn extend<'a, T>(&mut self, messages: T) -> Vec<usize>
+    where
+        T: IntoIterator<Item = &'a usize>,
+    {
+        messages
+            .into_iter()
+            .filter(|m| self.insert(**m))
+            .cloned()
+            .collect()
+    }
+    pub fn contains(&self, message: &usize) -> bool {
+        self.known.contains(message)
+    }
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &usize> {
+        self.messages.iter()
+    }
+    /// Returns a copy of all messages for a "read_ok" Payload.
+    /// NOTE: The copy is not shared with the store, because replies stay in the replay cache
+    /// of exactly-once delivery and a shared copy would be cloned by the next insert.
+    pub fn snapshot(&self) -> Vec<usize> {
+        self.messages.clone()
+    }
+}
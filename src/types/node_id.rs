@@ -0,0 +1,202 @@
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+/// What kind of Node a Node Id belongs to.
+/// Ordered Server < Client < Service, Server and Client Nodes by their number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeKind {
+    /// "n" followed by the number of the Node (e.g. "n1").
+    Server(usize),
+    /// "c" followed by the number of the Client (e.g. "c1").
+    Client(usize),
+    /// Maelstrom services (e.g. "seq-kv", "lin-tso") and every other Node Id.
+    Service,
+}
+
+impl NodeKind {
+    fn detect(id: &str) -> Self {
+        let number = |prefix: char| {
+            id.strip_prefix(prefix)
+                .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|n| n.parse::<usize>().ok())
+        };
+
+        match (number('n'), number('c')) {
+            (Some(n), _) => Self::Server(n),
+            (_, Some(n)) => Self::Client(n),
+            _ => Self::Service,
+        }
+    }
+}
+
+/// Id of a Node in the network.
+/// Server and Client Node Ids are represented by their kind and number, all other Node Ids
+/// (e.g. services) are interned, so Node Ids can be copied, compared and hashed without
+/// touching the underlying str.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    kind: NodeKind,
+    /// The interned Node Id, None if it is the canonical Node Id of its kind (e.g. "n1", but
+    /// not "n01").
+    interned: Option<&'static str>,
+}
+
+impl NodeId {
+    pub fn new(id: &str) -> Self {
+        // NOTE: Interned Node Ids are never freed, which is fine because only the few services
+        // are interned. Client Node Ids are not, because every Client has a new one.
+        static INTERNED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+        let kind = NodeKind::detect(id);
+        if kind != NodeKind::Service && Self::is_canonical(id) {
+            return Self {
+                kind,
+                interned: None,
+            };
+        }
+
+        let mut interned = INTERNED
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let id: &'static str = match interned.get(id) {
+            Some(id) => id,
+            None => {
+                let id: &'static str = Box::leak(id.into());
+                interned.insert(id);
+                id
+            }
+        };
+
+        Self {
+            kind,
+            interned: Some(id),
+        }
+    }
+    /// Numbered Node Ids without leading zeros.
+    fn is_canonical(id: &str) -> bool {
+        id.len() == 2 || id.as_bytes().get(1) != Some(&b'0')
+    }
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+    /// Returns the number of Server and Client Nodes.
+    pub fn number(&self) -> Option<usize> {
+        match self.kind {
+            NodeKind::Server(n) | NodeKind::Client(n) => Some(n),
+            NodeKind::Service => None,
+        }
+    }
+    pub fn is_server(&self) -> bool {
+        matches!(self.kind, NodeKind::Server(_))
+    }
+    pub fn is_client(&self) -> bool {
+        matches!(self.kind, NodeKind::Client(_))
+    }
+    pub fn is_service(&self) -> bool {
+        matches!(self.kind, NodeKind::Service)
+    }
+}
+
+impl PartialEq<str> for NodeId {
+    fn eq(&self, other: &str) -> bool {
+        match self.interned {
+            Some(id) => id == other,
+            None => NodeKind::detect(other) == self.kind && Self::is_canonical(other),
+        }
+    }
+}
+
+impl PartialEq<&str> for NodeId {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl Ord for NodeId {
+    /// Orders by the NodeKind first, Node Ids with the same kind and number (e.g. "n1" and
+    /// "n01") by the Node Id.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind
+            .cmp(&other.kind)
+            .then_with(|| match (self.interned, other.interned) {
+                (None, None) => Ordering::Equal,
+                (Some(id), Some(other)) => id.cmp(other),
+                _ => self.to_string().cmp(&other.to_string()),
+            })
+    }
+}
+
+impl PartialOrd for NodeId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<&str> for NodeId {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<&String> for NodeId {
+    fn from(id: &String) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<String> for NodeId {
+    fn from(id: String) -> Self {
+        Self::new(&id)
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.interned, self.kind) {
+            (Some(id), _) => f.write_str(id),
+            (None, NodeKind::Server(n)) => write!(f, "n{}", n),
+            (None, NodeKind::Client(n)) => write!(f, "c{}", n),
+            (None, NodeKind::Service) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+impl Serialize for NodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeIdVisitor;
+
+        impl Visitor<'_> for NodeIdVisitor {
+            type Value = NodeId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a Node Id")
+            }
+            fn visit_str<E: de::Error>(self, id: &str) -> Result<NodeId, E> {
+                Ok(NodeId::new(id))
+            }
+        }
+
+        deserializer.deserialize_str(NodeIdVisitor)
+    }
+}
@@ -1,37 +1,139 @@
-use crate::types::message::Message;
+use crate::types::{
+    message::Message,
+    node_id::NodeId,
+    packet::Packet,
+    topology::{BroadcastTopology, IndexedTopology, Topology, TopologyStatus},
+};
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::Instant,
 };
 
 #[derive(Debug)]
 pub struct NodeInfo {
     /// The Node Id of this Node.
-    pub node_id: String,
+    pub node_id: NodeId,
     /// All Node Ids of Client Nodes in the network.
-    pub client_nodes: Vec<String>,
+    pub client_nodes: Vec<NodeId>,
     /// All Node Ids of Server Nodes in the network.
-    pub server_nodes: Vec<String>,
+    pub server_nodes: Vec<NodeId>,
     /// All Node Ids in the Network (Server and Client Nodes).
-    pub node_ids: Vec<String>,
-    /// The Topology given by Maelstrom.
-    pub topology: HashMap<String, Vec<String>>,
+    pub node_ids: Vec<NodeId>,
+    /// The Topology given by Maelstrom, adjusted by the TopologyStrategy of the PacketHandler.
+    pub topology: Topology,
+    /// The Topology between the Server Nodes as adjacency lists, built together with topology.
+    pub indexed_topology: IndexedTopology,
+    /// Whether the last received Topology was connected.
+    pub topology_status: TopologyStatus,
     /// Efficient Topology for sending Broadcast through network.
     /// Use the "src" Address of the Broadcast Packet to get the Node Ids that the broadcast should
     /// be forwarded to from this Node.
-    pub broadcast_topology: HashMap<String, Vec<String>>,
+    pub broadcast_topology: BroadcastTopology,
+    /// Nodes that are suspected to be dead, they are not used in the Broadcast Topology.
+    pub suspected_nodes: HashSet<NodeId>,
     /// The number of this node.
     pub node_number: usize,
     /// Internal number used for generating "msg_id" for packets (only increment).
     pub msg_number: usize,
-    pub conn_info: HashMap<String, NodeConnectionInfo>,
+    pub conn_info: HashMap<NodeId, NodeConnectionInfo>,
+    /// Requests of Client Nodes that have already been handled (only used for exactly-once
+    /// delivery).
+    pub client_conn_info: HashMap<NodeId, ClientConnectionInfo>,
 }
 
-#[derive(Debug)]
+impl NodeInfo {
+    /// Returns the neighbours of this Node in the Topology.
+    /// Falls back to all other Server Nodes if no Topology has been received yet.
+    pub fn get_neighbours(&self) -> Vec<&NodeId> {
+        match self.topology.get(&self.node_id) {
+            Some(neighbours) => neighbours.iter().collect(),
+            None => self
+                .conn_info
+                .keys()
+                .filter(|n| **n != self.node_id)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct NodeConnectionInfo {
+    /// The last msg_id that was sent to this Node.
     pub out_msg_id: usize,
+    /// Which msg_ids have been received from this Node.
     pub in_msg_id: MessageSyncStatus,
-    pub un_ack_messages: Vec<Message>,
+    /// Messages sent to this Node that have not been acknowledged yet and when they have been
+    /// sent last.
+    /// They are only removed once an Ack arrives, resending does not remove them.
+    pub un_ack_messages: Vec<(Message, Instant)>,
+    /// Messages from this Node that arrived after a gap and wait for the missing Messages
+    /// (only used for FIFO delivery).
+    pub reorder_buffer: BTreeMap<usize, Packet>,
+    /// When the last Packet sent by this Node itself (a Heartbeat or a Message with msg_id) has
+    /// been received.
+    pub last_heard: Option<Instant>,
+    /// The Nodes this Node had not heard from when it sent its last Heartbeat.
+    pub suspected: HashSet<NodeId>,
+}
+
+impl NodeConnectionInfo {
+    /// Decides which Messages can be handled in order after the Message with msg_id has been
+    /// tracked by in_msg_id.
+    /// Returns the Packet and all buffered Packets before the first missing msg_id, or buffers
+    /// the Packet if an earlier Message is still missing.
+    pub fn release_in_order(&mut self, msg_id: usize, packet: Packet) -> Vec<Packet> {
+        match self.in_msg_id.get_first_missing_msg_id() {
+            Some(first_missing) if first_missing < msg_id => {
+                self.reorder_buffer.insert(msg_id, packet);
+                Vec::with_capacity(0)
+            }
+            first_missing => {
+                let mut packets = vec![packet];
+                let first_missing = first_missing.unwrap_or(usize::MAX);
+                while let Some(entry) = self.reorder_buffer.first_entry() {
+                    if *entry.key() > first_missing {
+                        break;
+                    }
+                    packets.push(entry.remove());
+                }
+                packets
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ClientConnectionInfo {
+    /// The msg_ids of the last handled requests with the responses that were sent for them.
+    /// Requests without responses have not been answered yet.
+    pub requests: VecDeque<(usize, Vec<Packet>)>,
+}
+
+impl ClientConnectionInfo {
+    pub fn get_responses(&self, msg_id: usize) -> Option<&Vec<Packet>> {
+        self.requests
+            .iter()
+            .find(|(id, _)| *id == msg_id)
+            .map(|(_, responses)| responses)
+    }
+    /// Remembers a request, forgetting the oldest one if more than window requests are stored.
+    pub fn add_request(&mut self, msg_id: usize, window: usize) {
+        self.requests.push_back((msg_id, Vec::new()));
+        while self.requests.len() > window {
+            self.requests.pop_front();
+        }
+    }
+    /// Stores a response so it can be resent if the request is retried.
+    pub fn add_response(&mut self, packet: &Packet) {
+        if let Some(in_reply_to) = packet.body.in_reply_to {
+            if let Some((_, responses)) =
+                self.requests.iter_mut().find(|(id, _)| *id == in_reply_to)
+            {
+                responses.push(packet.clone());
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -45,14 +147,17 @@ pub enum MessageSyncStatus {
     },
 }
 
+impl Default for MessageSyncStatus {
+    fn default() -> Self {
+        Self::Synced { last_msg_id: 0 }
+    }
+}
+
 // TODO: Change the function names to better represent their behaviour.
 impl MessageSyncStatus {
+    /// Compares the msg_id with the next expected msg_id.
     pub fn is_next_msg_id(&self, msg_id: usize) -> Ordering {
-        match self {
-            Self::Synced { last_msg_id } | Self::NotSynced { last_msg_id, .. } => {
-                last_msg_id.cmp(&msg_id)
-            }
-        }
+        msg_id.cmp(&self.get_next_msg_id())
     }
     pub fn get_next_msg_id(&self) -> usize {
         match self {
@@ -71,6 +176,27 @@ impl MessageSyncStatus {
     pub fn is_synced(&self) -> bool {
         matches!(self, Self::Synced { .. })
     }
+    pub fn get_first_missing_msg_id(&self) -> Option<usize> {
+        match self {
+            Self::Synced { .. } => None,
+            Self::NotSynced {
+                missing_msg_ids, ..
+            } => missing_msg_ids.iter().min().cloned(),
+        }
+    }
+    /// Returns the msg_ids that have not been received yet in ascending order.
+    pub fn get_missing_msg_ids(&self) -> Vec<usize> {
+        match self {
+            Self::Synced { .. } => Vec::with_capacity(0),
+            Self::NotSynced {
+                missing_msg_ids, ..
+            } => {
+                let mut msg_ids: Vec<usize> = missing_msg_ids.iter().cloned().collect();
+                msg_ids.sort_unstable();
+                msg_ids
+            }
+        }
+    }
     pub fn increment_msg_id(&mut self) {
         match self {
             Self::Synced { last_msg_id } | Self::NotSynced { last_msg_id, .. } => *last_msg_id += 1,
@@ -81,8 +207,8 @@ impl MessageSyncStatus {
             Self::Synced { last_msg_id } => {
                 if *last_msg_id < msg_id {
                     *self = Self::NotSynced {
+                        missing_msg_ids: (*last_msg_id + 1..msg_id).collect(),
                         last_msg_id: msg_id,
-                        missing_msg_ids: (*last_msg_id..msg_id).collect(),
                     }
                 }
             }
@@ -91,10 +217,28 @@ impl MessageSyncStatus {
                 missing_msg_ids,
             } => {
                 if *last_msg_id < msg_id {
+                    missing_msg_ids.extend(*last_msg_id + 1..msg_id);
                     *last_msg_id = msg_id;
-                    missing_msg_ids.extend(*last_msg_id..msg_id);
                 }
             }
         }
     }
+    /// Marks a missing msg_id as received.
+    /// Returns false if the msg_id was not missing, meaning the message is a duplicate.
+    pub fn remove_missing_msg_id(&mut self, msg_id: usize) -> bool {
+        match self {
+            Self::Synced { .. } => false,
+            Self::NotSynced {
+                last_msg_id,
+                missing_msg_ids,
+            } => {
+                let removed = missing_msg_ids.remove(&msg_id);
+                if missing_msg_ids.is_empty() {
+                    let last_msg_id = *last_msg_id;
+                    *self = Self::Synced { last_msg_id };
+                }
+                removed
+            }
+        }
+    }
 }
@@ -1,9 +1,9 @@
-use crate::types::message::Message;
+use crate::types::{message::Message, node_id::NodeId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Packet {
-    pub src: String,
-    pub dest: String,
+    pub src: NodeId,
+    pub dest: NodeId,
     pub body: Message,
 }
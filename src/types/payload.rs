@@ -1,15 +1,24 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use crate::types::{message::Message, packet::Packet};
+use crate::{
+    paxos::{AcceptedValue, Ballot},
+    raft::log::LogEntry,
+    two_phase_commit::TxnId,
+    txn::{hlc::Timestamp, MicroOp},
+    types::{
+        id_strategy::UniqueId, message::Message, node_id::NodeId, packet::Packet,
+        topology::Topology,
+    },
+};
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Payload {
     // NOTE: Standard Payloads
     Init {
-        node_id: String,
-        node_ids: Vec<String>,
+        node_id: NodeId,
+        node_ids: Vec<NodeId>,
     },
     InitOk,
     Echo {
@@ -20,26 +29,50 @@ pub enum Payload {
     },
     Generate,
     GenerateOk {
-        id: usize,
+        id: UniqueId,
     },
     Broadcast {
         message: usize,
     },
     BroadcastOk,
-    Read,
-    ReadOk {
-        messages: Vec<usize>,
+    /// The key is only set for key-value workloads.
+    Read {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<serde_json::Value>,
+    },
+    ReadOk(ReadValue),
+    Add(AddValue),
+    AddOk,
+    Write {
+        key: serde_json::Value,
+        value: serde_json::Value,
     },
+    WriteOk,
+    Cas {
+        key: serde_json::Value,
+        from: serde_json::Value,
+        to: serde_json::Value,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        create_if_not_exists: bool,
+    },
+    CasOk,
     Topology {
-        topology: HashMap<String, Vec<String>>,
+        topology: Topology,
     },
     TopologyOk,
     Error {
         code: usize,
         text: String,
     },
+    // NOTE: Maelstrom service Payloads
+    Ts,
+    TsOk {
+        ts: u64,
+    },
     // NOTE: Custom Payloads
-    SyncRequest,
+    SyncRequest {
+        msg_ids: Vec<usize>,
+    },
     Batch {
         messages: Vec<Message>,
     },
@@ -50,4 +83,196 @@ pub enum Payload {
     Forward {
         packet: Box<Packet>,
     },
+    /// The messages of the source Node the destination is not known to have, which are the
+    /// first up_to messages of the source Node without the first acked ones. The source Node
+    /// has received the first "received" messages of the destination. Answered with GossipOk
+    /// with the same fields (see PeerSync).
+    Gossip {
+        messages: Vec<usize>,
+        up_to: usize,
+        received: usize,
+    },
+    GossipOk {
+        messages: Vec<usize>,
+        up_to: usize,
+        received: usize,
+    },
+    /// Sent to every Server Node on tick if a suspect timeout is set, with the Nodes the
+    /// source Node has not heard from (see BroadcastRedundancy).
+    Heartbeat {
+        suspected: Vec<NodeId>,
+    },
+    /// The same fields as Gossip. Answered with BroadcastDeltaOk.
+    BroadcastDelta {
+        messages: Vec<usize>,
+        up_to: usize,
+        received: usize,
+    },
+    /// The source Node has received the first "received" messages of the destination.
+    BroadcastDeltaOk {
+        received: usize,
+    },
+    /// Raft, the candidate is the source Node.
+    RequestVote {
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    },
+    RequestVoteOk {
+        term: u64,
+        vote_granted: bool,
+    },
+    /// Raft, the leader is the source Node.
+    AppendEntries {
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<LogEntry>,
+        leader_commit: u64,
+    },
+    /// Raft, sent instead of AppendEntries if the entries the follower is missing have been
+    /// compacted into the snapshot. Answered with AppendEntriesOk.
+    InstallSnapshot {
+        term: u64,
+        last_included_index: u64,
+        last_included_term: u64,
+        snapshot: serde_json::Value,
+    },
+    /// The match_index is the index of the last appended entry on success, otherwise the
+    /// index of the last entry in the log of the follower.
+    AppendEntriesOk {
+        term: u64,
+        success: bool,
+        match_index: u64,
+    },
+    /// Multi-Paxos phase 1, the proposer is the source Node. The acceptor only needs to
+    /// promise the values accepted after its commit_index.
+    Prepare {
+        ballot: Ballot,
+        commit_index: u64,
+    },
+    Promise {
+        ballot: Ballot,
+        accepted: Vec<AcceptedValue>,
+    },
+    /// Multi-Paxos phase 2, the leader is the source Node.
+    Accept {
+        ballot: Ballot,
+        slot: u64,
+        value: serde_json::Value,
+    },
+    Accepted {
+        ballot: Ballot,
+        slot: u64,
+    },
+    /// Multi-Paxos, the chosen values starting at first_slot. Also sent as the heartbeat of
+    /// the leader.
+    Decide {
+        ballot: Ballot,
+        first_slot: u64,
+        values: Vec<serde_json::Value>,
+    },
+    DecideOk {
+        ballot: Ballot,
+        commit_index: u64,
+    },
+    /// Multi-Paxos, answers a Prepare or an Accept with a lower ballot than the promised one.
+    Rejected {
+        ballot: Ballot,
+    },
+    /// Keys (and their values) the source Node no longer owns after the rebalance of the
+    /// epoch. Answered with ShardHandoffOk.
+    ShardHandoff {
+        epoch: u64,
+        entries: Vec<(serde_json::Value, serde_json::Value)>,
+    },
+    ShardHandoffOk {
+        epoch: u64,
+    },
+    GSetAdd {
+        element: i64,
+    },
+    /// Two-phase commit, the coordinator sends the operations of the participant.
+    TxnPrepare {
+        txn_id: TxnId,
+        operations: serde_json::Value,
+    },
+    TxnVote {
+        txn_id: TxnId,
+        commit: bool,
+    },
+    TxnCommit {
+        txn_id: TxnId,
+    },
+    TxnAbort {
+        txn_id: TxnId,
+    },
+    /// Two-phase commit, a participant asks the coordinator for the decision. Answered with
+    /// TxnCommit or TxnAbort once the transaction has been decided.
+    TxnQuery {
+        txn_id: TxnId,
+    },
+    Txn {
+        txn: Vec<MicroOp>,
+    },
+    TxnOk {
+        txn: Vec<MicroOp>,
+    },
+    /// Asks the certifier to commit the appends of a transaction that read from the snapshot
+    /// at start_ts. Answered with TxnCertified.
+    TxnCertify {
+        number: u64,
+        start_ts: Timestamp,
+        appends: BTreeMap<i64, Vec<i64>>,
+    },
+    /// The index of the commit in the log of the certifier or the conflicting key.
+    TxnCertified {
+        number: u64,
+        result: Result<u64, i64>,
+    },
+    /// The commit at the index of the log of the certifier.
+    TxnCommitted {
+        index: u64,
+        commit_ts: Timestamp,
+        appends: BTreeMap<i64, Vec<i64>>,
+    },
+    /// Asks the certifier for the commits after the index (the number of installed commits).
+    TxnSync {
+        index: u64,
+    },
+    /// State (or delta) of the Crdt "name", see CrdtReplicator.
+    CrdtState {
+        name: String,
+        state: serde_json::Value,
+    },
+    CrdtStateOk {
+        name: String,
+        state: serde_json::Value,
+    },
+}
+
+/// The "read_ok" Payloads of the different workloads.
+/// NOTE: Every "value" is deserialized as Value (e.g. the replies of the key-value services),
+/// Counter and Elements are only built by the handlers of their workloads.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ReadValue {
+    /// Broadcast
+    Messages { messages: Vec<usize> },
+    /// Key-value workloads
+    Value { value: serde_json::Value },
+    /// PN-Counter
+    Counter { value: i64 },
+    /// G-Set
+    Elements { value: Vec<i64> },
+}
+
+/// The "add" Payloads of the different workloads.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AddValue {
+    /// PN-Counter
+    Delta { delta: i64 },
+    /// G-Set
+    Element { element: i64 },
 }
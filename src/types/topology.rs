@@ -1,4 +1,158 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
-pub type BroadcastTopology = HashMap<String, Vec<String>>;
-pub type Topology = HashMap<String, Vec<String>>;
+use crate::types::node_id::NodeId;
+
+pub type BroadcastTopology = HashMap<NodeId, Vec<NodeId>>;
+pub type Topology = HashMap<NodeId, Vec<NodeId>>;
+
+/// How a Node derives the Topology it uses from the Topology given by Maelstrom.
+/// Every strategy only depends on the Server Node Ids, so all Nodes derive the same Topology
+/// without coordination.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TopologyStrategy {
+    /// Use the Topology given by Maelstrom.
+    #[default]
+    AsGiven,
+    /// Use a breadth-first spanning tree of the given Topology rooted at the lowest Node Id.
+    SpanningTree,
+    /// Connect every Node to the Node with the lowest Node Id.
+    Star,
+    /// Arrange the Nodes, ordered by Node Id, as a complete tree where every Node has up to k
+    /// children.
+    KAryTree(usize),
+    /// Connect every Node to every other Node.
+    FullyConnected,
+}
+
+/// What to do if the Topology consists of multiple non-connected components.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DisconnectedTopologyPolicy {
+    /// Keep the previous Topology and respond with an error.
+    Reject,
+    /// Connect the components by adding an edge between the lowest Node Ids of consecutive
+    /// components.
+    #[default]
+    Repair,
+}
+
+/// Result of the connectivity analysis of the last received Topology.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TopologyStatus {
+    /// No Topology has been received yet.
+    #[default]
+    Unknown,
+    Connected,
+    /// The Topology was disconnected and has been connected using the bridge edges.
+    Repaired {
+        components: Vec<Vec<NodeId>>,
+        bridges: Vec<(NodeId, NodeId)>,
+    },
+    /// The Topology was disconnected and has not been used.
+    Rejected {
+        components: Vec<Vec<NodeId>>,
+    },
+}
+
+/// How many independent paths Broadcasts are sent along.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BroadcastRedundancy {
+    /// Number of edge-disjoint paths from every origin to every other Node.
+    /// Broadcasts still reach every Node if up to "paths - 1" links fail.
+    /// Only uses the minimal Broadcast Topology if 1.
+    pub paths: usize,
+    /// Server Nodes that have not sent anything for this long are suspected to be dead and the
+    /// Broadcast Topology is rebuilt without them (requires a tick interval).
+    /// Every Node sends a Heartbeat to all other Server Nodes every third of the timeout, so
+    /// quiet Nodes are not suspected. The Heartbeats carry the Nodes the sender has not heard
+    /// from and every Node also suspects those, so all Nodes build the same Broadcast
+    /// Topologies once the Heartbeats have arrived.
+    pub suspect_timeout: Option<Duration>,
+}
+
+impl Default for BroadcastRedundancy {
+    fn default() -> Self {
+        Self {
+            paths: 1,
+            suspect_timeout: None,
+        }
+    }
+}
+
+/// The Topology as adjacency lists of Node indices, so graph algorithms do not have to hash or
+/// clone Node Ids.
+/// The Nodes are sorted by their Node Id, so comparing indices is the same as comparing the
+/// Node Ids. Links are used in both directions, even if the Topology only contains one.
+#[derive(Clone, Debug, Default)]
+pub struct IndexedTopology {
+    pub nodes: Vec<NodeId>,
+    pub adjacency: Vec<Vec<usize>>,
+    index: HashMap<NodeId, usize>,
+}
+
+impl IndexedTopology {
+    /// Only links between the given Nodes are used.
+    pub fn new(all_nodes: &[NodeId], topology: &Topology) -> Self {
+        let mut nodes = all_nodes.to_vec();
+        nodes.sort();
+        nodes.dedup();
+        let index: HashMap<NodeId, usize> =
+            nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+        for (node, neighbours) in topology.iter() {
+            for neighbour in neighbours {
+                if let (Some(&n1), Some(&n2)) = (index.get(node), index.get(neighbour)) {
+                    if n1 == n2 {
+                        continue;
+                    }
+                    adjacency[n1].push(n2);
+                    adjacency[n2].push(n1);
+                }
+            }
+        }
+        for neighbours in adjacency.iter_mut() {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+
+        Self {
+            nodes,
+            adjacency,
+            index,
+        }
+    }
+    /// Returns the Topology without the links to the excluded Nodes, which keep their index.
+    pub fn without_links_to(&self, excluded: &HashSet<NodeId>) -> Self {
+        let excluded: Vec<bool> = self.nodes.iter().map(|n| excluded.contains(n)).collect();
+        let adjacency = self
+            .adjacency
+            .iter()
+            .enumerate()
+            .map(|(n, neighbours)| match excluded[n] {
+                true => Vec::new(),
+                false => neighbours
+                    .iter()
+                    .filter(|m| !excluded[**m])
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+        Self {
+            nodes: self.nodes.clone(),
+            adjacency,
+            index: self.index.clone(),
+        }
+    }
+    pub fn index_of(&self, node: &NodeId) -> Option<usize> {
+        self.index.get(node).cloned()
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}